@@ -0,0 +1,192 @@
+use crate::{open, write_line, InputError, Line, LineTransformer, MyResult};
+use regex::bytes::Regex;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+pub(crate) const DEFAULT_PATTERN: &str = r#"^\s*[#!]include\s+"?([^"]+?)"?\s*$"#;
+
+// The include directive regex must capture the path in its first group.
+pub(crate) fn parse_pattern(pattern: &str) -> Result<Regex, String> {
+    let re = Regex::new(pattern).map_err(|e| e.to_string())?;
+    if re.captures_len() < 2 {
+        return Err("pattern must capture the included path in a group".to_string());
+    }
+    Ok(re)
+}
+
+/// Settings for --follow-includes.
+pub(crate) struct Includes<'a> {
+    pub pattern: &'a Regex,
+    pub markers: bool,
+    pub decompress: bool,
+}
+
+// A file that is being expanded; the stack of these is the include chain.
+struct Frame {
+    name: String,
+    canonical: Option<PathBuf>,
+}
+
+/// Writes `read` like `transform_lines`, replacing every include directive
+/// with the contents of the file it names. Relative paths are resolved
+/// against the directory of the including file.
+pub(crate) fn cat_with_includes(
+    filename: &str,
+    read: Box<dyn BufRead>,
+    includes: &Includes,
+    transformers: &mut [Box<dyn LineTransformer>],
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let canonical = match filename {
+        "-" => None,
+        _ => fs::canonicalize(filename).ok(),
+    };
+    let mut stack = vec![Frame {
+        name: filename.to_string(),
+        canonical,
+    }];
+    expand(read, includes, &mut stack, transformers, out)
+}
+
+fn expand(
+    mut read: Box<dyn BufRead>,
+    includes: &Includes,
+    stack: &mut Vec<Frame>,
+    transformers: &mut [Box<dyn LineTransformer>],
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let mut line = Line::default();
+    let mut line_num = 0;
+    // Markers are written lazily so that none is left dangling at the end
+    // of a file or in front of another directive.
+    let mut need_marker = includes.markers;
+    loop {
+        match line.read_from(&mut read) {
+            Ok(0) => return Ok(()),
+            Ok(_) => line_num += 1,
+            Err(err) if stack.len() == 1 => return Err(InputError::Read(err).into()),
+            Err(err) => {
+                let name = &stack.last().unwrap().name;
+                return Err(InputError::Include(format!(
+                    "Failed to read {}: {}",
+                    name, err
+                ))
+                .into());
+            }
+        }
+
+        match directive(includes.pattern, &line.bytes) {
+            None => {
+                if need_marker {
+                    let name = &stack.last().unwrap().name;
+                    writeln!(out, "# from {}:{}", name, line_num)?;
+                    need_marker = false;
+                }
+                write_line(&mut line, transformers, out)?;
+            }
+            Some(target) => {
+                include(&target, line_num, includes, stack, transformers, out)?;
+                need_marker = includes.markers;
+            }
+        }
+    }
+}
+
+fn include(
+    target: &str,
+    line_num: usize,
+    includes: &Includes,
+    stack: &mut Vec<Frame>,
+    transformers: &mut [Box<dyn LineTransformer>],
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let parent = &stack.last().unwrap().name;
+    let path = resolve(parent, target);
+    let name = path.display().to_string();
+    let failed = |err: &dyn std::fmt::Display| {
+        InputError::Include(format!(
+            "Failed to include {} from {}:{}: {}",
+            name, parent, line_num, err
+        ))
+    };
+
+    let canonical = fs::canonicalize(&path).map_err(|e| failed(&e))?;
+    if stack.iter().any(|f| f.canonical.as_ref() == Some(&canonical)) {
+        let chain: Vec<_> = stack
+            .iter()
+            .map(|f| f.name.as_str())
+            .chain([name.as_str()])
+            .collect();
+        return Err(InputError::Include(format!(
+            "Include cycle: {}",
+            chain.join(" -> ")
+        ))
+        .into());
+    }
+    let read = open(&name, includes.decompress).map_err(|e| failed(&e))?;
+
+    stack.push(Frame {
+        name,
+        canonical: Some(canonical),
+    });
+    expand(read, includes, stack, transformers, out)?;
+    stack.pop();
+    Ok(())
+}
+
+// Returns the path named by an include directive, if `line` is one.
+fn directive(pattern: &Regex, line: &[u8]) -> Option<String> {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    pattern
+        .captures(line)
+        .and_then(|caps| caps.get(1))
+        .map(|target| String::from_utf8_lossy(target.as_bytes()).into_owned())
+}
+
+fn resolve(parent: &str, target: &str) -> PathBuf {
+    let target = Path::new(target);
+    match Path::new(parent).parent() {
+        Some(dir) if parent != "-" && target.is_relative() => dir.join(target),
+        _ => target.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{directive, parse_pattern, resolve, DEFAULT_PATTERN};
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_parse_pattern() {
+        assert!(parse_pattern(DEFAULT_PATTERN).is_ok());
+        assert!(parse_pattern(r"^@import (\S+)$").is_ok());
+        assert!(parse_pattern(r"^@import \S+$").is_err());
+        assert!(parse_pattern(r"^@import (\S+$").is_err());
+    }
+
+    #[test]
+    fn test_directive() {
+        let re = parse_pattern(DEFAULT_PATTERN).unwrap();
+        let target = |line: &[u8]| directive(&re, line);
+        assert_eq!(target(b"#include other.conf\n"), Some("other.conf".into()));
+        assert_eq!(target(b"!include path\r\n"), Some("path".into()));
+        assert_eq!(target(b"  #include \"a b.conf\""), Some("a b.conf".into()));
+        assert_eq!(target(b"# include other.conf\n"), None);
+        assert_eq!(target(b"#included\n"), None);
+        assert_eq!(target(b"x = 1 #include other.conf\n"), None);
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("a.conf", "b.conf"), PathBuf::from("b.conf"));
+        assert_eq!(
+            resolve("conf/a.conf", "d/b.conf"),
+            PathBuf::from("conf/d/b.conf")
+        );
+        assert_eq!(resolve("conf/a.conf", "/etc/b.conf"), PathBuf::from("/etc/b.conf"));
+        assert_eq!(resolve("-", "b.conf"), PathBuf::from("b.conf"));
+    }
+}
@@ -1,10 +1,27 @@
+mod include;
+mod select;
+mod transform;
+
 use std::error::Error;
+use std::fmt;
 use clap::Parser;
+use include::Includes;
+use regex::bytes::Regex;
+use select::{Selection, Span, Unit};
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{
+    self, BufRead, BufReader, BufWriter, Cursor, Read, StdoutLock, Write,
+};
+
+pub use transform::{
+    Line, LineTransformer, NumberFormat, MAX_NUMBER_WIDTH, NumberLines, NumberNonblank,
+    NumberStyle, ShowEnds, ShowNonprinting, ShowTabs, SqueezeBlank,
+};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+const COPY_BUF_SIZE: usize = 256 * 1024;
+
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
 /// Rust version of cat
@@ -17,6 +34,126 @@ pub struct Config {
     /// Number non-blank lines
     #[arg(short('b'), long)]
     number_nonblank: bool,
+    /// Keep numbering lines across files instead of restarting at 1
+    #[arg(long)]
+    continue_numbering: bool,
+    /// Width of line numbers
+    #[arg(
+        long,
+        default_value_t = 6,
+        value_parser = clap::value_parser!(u64).range(1..=MAX_NUMBER_WIDTH as u64),
+        value_name = "WIDTH",
+    )]
+    number_width: u64,
+    /// Text between a line number and the line
+    #[arg(long, default_value = "\t", value_name = "SEP")]
+    number_separator: String,
+    /// First line number
+    #[arg(long, default_value_t = 1, allow_negative_numbers = true, value_name = "NUMBER")]
+    number_start: i64,
+    /// Line number increment
+    #[arg(long, default_value_t = 1, allow_negative_numbers = true, value_name = "NUMBER")]
+    number_increment: i64,
+    /// Line number format
+    #[arg(long, value_enum, default_value_t = NumberFormat::Rn, value_name = "FORMAT")]
+    number_format: NumberFormat,
+    /// Suppress repeated empty output lines
+    #[arg(short('s'), long)]
+    squeeze_blank: bool,
+    /// Do not decompress gzip, bzip2, xz or zstd input
+    #[arg(long)]
+    no_decompress: bool,
+    /// Only print lines START:END (1-based, inclusive, negative counts
+    /// from the end)
+    #[arg(
+        long,
+        value_name = "START:END",
+        value_parser = select::parse_span,
+        allow_hyphen_values = true,
+        conflicts_with_all = ["bytes", "follow_includes"],
+    )]
+    lines: Option<Span>,
+    /// Only print bytes START:END (1-based, inclusive, negative counts
+    /// from the end)
+    #[arg(
+        long,
+        value_name = "START:END",
+        value_parser = select::parse_span,
+        allow_hyphen_values = true,
+        conflicts_with = "follow_includes",
+    )]
+    bytes: Option<Span>,
+    /// Replace include directives with the contents of the named file
+    #[arg(long)]
+    follow_includes: bool,
+    /// Regex matching an include directive; group 1 is the path
+    #[arg(
+        long,
+        default_value = include::DEFAULT_PATTERN,
+        value_parser = include::parse_pattern,
+        value_name = "REGEX",
+        requires = "follow_includes",
+    )]
+    include_pattern: Regex,
+    /// Mark where included text comes from with "# from FILE:LINE"
+    #[arg(long, requires = "follow_includes")]
+    include_markers: bool,
+    /// Equivalent to -vET
+    #[arg(short('A'), long)]
+    show_all: bool,
+    /// Equivalent to -vE
+    #[arg(short('e'))]
+    show_nonprinting_ends: bool,
+    /// Equivalent to -vT
+    #[arg(short('t'))]
+    show_nonprinting_tabs: bool,
+    /// Display $ at end of each line
+    #[arg(short('E'), long)]
+    show_ends: bool,
+    /// Display TAB characters as ^I
+    #[arg(short('T'), long)]
+    show_tabs: bool,
+    /// Use ^ and M- notation, except for LFD and TAB
+    #[arg(short('v'), long)]
+    show_nonprinting: bool,
+}
+
+impl Config {
+    // -A, -e and -t are shorthands, so the effective settings are derived
+    // here rather than in get_args; that way a Config built with
+    // Config::parse_from behaves the same when embedded.
+    fn show_ends(&self) -> bool {
+        self.show_ends || self.show_all || self.show_nonprinting_ends
+    }
+
+    fn show_tabs(&self) -> bool {
+        self.show_tabs || self.show_all || self.show_nonprinting_tabs
+    }
+
+    fn show_nonprinting(&self) -> bool {
+        self.show_nonprinting
+            || self.show_all
+            || self.show_nonprinting_ends
+            || self.show_nonprinting_tabs
+    }
+
+    fn selection(&self) -> Option<Selection> {
+        match (self.lines, self.bytes) {
+            (Some(span), _) => Some(Selection { unit: Unit::Lines, span }),
+            (_, Some(span)) => Some(Selection { unit: Unit::Bytes, span }),
+            _ => None,
+        }
+    }
+
+    fn number_style(&self) -> NumberStyle {
+        NumberStyle {
+            width: self.number_width as usize,
+            separator: self.number_separator.clone(),
+            start: self.number_start,
+            increment: self.number_increment,
+            format: self.number_format,
+        }
+    }
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -24,35 +161,686 @@ pub fn get_args() -> MyResult<Config> {
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    for filename in config.files {
-        match open(&filename) {
-            Err(err) => eprintln!("Failed to open {}: {}", filename, err),
-            Ok(read) => {
-                let mut line_number = 0;
-                read.lines().for_each(|line| {
-                let line = line.unwrap();
-                let is_blank = line.trim().is_empty();
-
-                if is_blank && config.number_nonblank {
-                    println!("");
-                } else {
-                    line_number += 1;
-                    if config.number || config.number_nonblank {
-                        println!("{:>6}\t{}" , line_number, line);
-                    } else {
-                        println!("{}", line);
-                    }
-                }
-            })},
+    let mut out = io::stdout().lock();
+    cat_files(&config, &mut out, copy_plain)
+}
+
+/// Like `run`, but writes to `out` instead of stdout.
+pub fn run_to_writer(config: &Config, mut out: impl Write) -> MyResult<()> {
+    cat_files(config, &mut out, |filename, decompress, out| {
+        let read = open(filename, decompress)?;
+        transform_lines(read, &mut [], out)
+    })
+}
+
+/// Builds the chain of line transformers selected by `config`, in the
+/// order they have to be applied.
+pub fn transformers(config: &Config) -> Vec<Box<dyn LineTransformer>> {
+    let mut transformers: Vec<Box<dyn LineTransformer>> = Vec::new();
+    if config.squeeze_blank {
+        transformers.push(Box::<SqueezeBlank>::default());
+    }
+    if config.number {
+        transformers.push(Box::new(NumberLines::new(config.number_style())));
+    } else if config.number_nonblank {
+        transformers.push(Box::new(NumberNonblank::new(config.number_style())));
+    }
+    if config.show_tabs() {
+        transformers.push(Box::<ShowTabs>::default());
+    }
+    if config.show_nonprinting() {
+        transformers.push(Box::<ShowNonprinting>::default());
+    }
+    if config.show_ends() {
+        transformers.push(Box::new(ShowEnds));
+    }
+    transformers
+}
+
+/// Copies `read` to `out`, passing each line through `transformers`.
+/// Lines are handled as raw bytes including their terminator, so invalid
+/// UTF-8, CRLF endings and a missing final newline all pass through as-is.
+pub fn transform_lines(
+    mut read: impl BufRead,
+    transformers: &mut [Box<dyn LineTransformer>],
+    out: &mut impl Write,
+) -> MyResult<()> {
+    if transformers.is_empty() {
+        loop {
+            let buf = read.fill_buf().map_err(InputError::Read)?;
+            if buf.is_empty() {
+                return Ok(());
+            }
+            out.write_all(buf)?;
+            let len = buf.len();
+            read.consume(len);
         }
     }
+
+    let mut line = Line::default();
+    while line.read_from(&mut read).map_err(InputError::Read)? != 0 {
+        write_line(&mut line, transformers, out)?;
+    }
     Ok(())
 }
 
+// Like transform_lines, but only writes the lines (or parts of lines)
+// inside `selection`. Every line still goes through the transformers so
+// that line numbers are those of the source. With `stop_early`, reading
+// ends as soon as the selection has been passed.
+fn select_lines(
+    mut read: Box<dyn BufRead>,
+    selection: &Selection,
+    total: Option<u64>,
+    stop_early: bool,
+    transformers: &mut [Box<dyn LineTransformer>],
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let (first, last) = selection.span.resolve(total);
+    let mut line = Line::default();
+    let mut line_num = 0;
+    let mut offset = 0;
+    loop {
+        let len = line.read_from(&mut read).map_err(InputError::Read)? as u64;
+        if len == 0 {
+            return Ok(());
+        }
+        line_num += 1;
+        let (selected, done) = match selection.unit {
+            Unit::Lines => ((first..=last).contains(&line_num), line_num >= last),
+            Unit::Bytes => (
+                select::clip(&mut line.bytes, offset, first, last),
+                offset + len >= last,
+            ),
+        };
+        offset += len;
+
+        if transformers.iter_mut().all(|t| t.transform(&mut line)) && selected {
+            out.write_all(&line.prefix)?;
+            out.write_all(&line.bytes)?;
+        }
+        if done && stop_early {
+            return Ok(());
+        }
+    }
+}
+
+// Opens a file for select_lines along with its total size when the
+// selection counts from the end. Named files are counted in a separate
+// pass; stdin can only be read once, so it is held in memory instead.
+fn open_selection(
+    filename: &str,
+    decompress: bool,
+    selection: &Selection,
+) -> MyResult<(Box<dyn BufRead>, Option<u64>)> {
+    let read = open(filename, decompress)?;
+    if !selection.span.needs_total() {
+        return Ok((read, None));
+    }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
+    if filename == "-" {
+        let mut read = read;
+        let mut buffer = Vec::new();
+        read.read_to_end(&mut buffer).map_err(InputError::Read)?;
+        let total = select::count_total(buffer.as_slice(), selection.unit)
+            .map_err(InputError::Read)?;
+        Ok((Box::new(Cursor::new(buffer)), Some(total)))
+    } else {
+        let total = select::count_total(read, selection.unit)
+            .map_err(InputError::Read)?;
+        Ok((open(filename, decompress)?, Some(total)))
+    }
+}
+
+fn write_line(
+    line: &mut Line,
+    transformers: &mut [Box<dyn LineTransformer>],
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if transformers.iter_mut().all(|t| t.transform(line)) {
+        out.write_all(&line.prefix)?;
+        out.write_all(&line.bytes)?;
+    }
+    Ok(())
+}
+
+// Concatenates every input file, using `copy_plain` when no line
+// transformers are needed.
+fn cat_files<W: Write>(
+    config: &Config,
+    out: &mut W,
+    copy_plain: impl Fn(&str, bool, &mut W) -> MyResult<()>,
+) -> MyResult<()> {
+    let mut transformers = transformers(config);
+    let mut num_failed = 0;
+    for filename in &config.files {
+        if !config.continue_numbering {
+            transformers.iter_mut().for_each(|t| t.start_file());
+        }
+        let decompress = !config.no_decompress;
+        let result = if config.follow_includes {
+            let includes = Includes {
+                pattern: &config.include_pattern,
+                markers: config.include_markers,
+                decompress,
+            };
+            open(filename, decompress).and_then(|read| {
+                include::cat_with_includes(
+                    filename,
+                    read,
+                    &includes,
+                    &mut transformers,
+                    out,
+                )
+            })
+        } else if let Some(selection) = config.selection() {
+            open_selection(filename, decompress, &selection).and_then(|(read, total)| {
+                select_lines(
+                    read,
+                    &selection,
+                    total,
+                    !config.continue_numbering,
+                    &mut transformers,
+                    out,
+                )
+            })
+        } else if transformers.is_empty() {
+            copy_plain(filename, decompress, out)
+        } else {
+            open(filename, decompress)
+                .and_then(|read| transform_lines(read, &mut transformers, out))
+        };
+        if let Err(err) = result {
+            match err.downcast_ref::<InputError>() {
+                Some(InputError::Open(err)) => {
+                    eprintln!("Failed to open {}: {}", filename, err)
+                }
+                Some(InputError::Read(err)) => {
+                    eprintln!("Failed to read {}: {}", filename, err)
+                }
+                Some(InputError::Include(msg)) => eprintln!("{}", msg),
+                None => return Err(err),
+            }
+            num_failed += 1;
+        }
+    }
+
+    match num_failed {
+        0 => Ok(()),
+        1 => Err("1 input could not be read".into()),
+        n => Err(format!("{} inputs could not be read", n).into()),
+    }
+}
+
+fn open(filename: &str, decompress: bool) -> MyResult<Box<dyn BufRead>> {
+    let mut read: Box<dyn BufRead> = match filename {
+        "-" => Box::new(BufReader::new(io::stdin())),
+        _ => Box::new(BufReader::new(
+            File::open(filename).map_err(InputError::Open)?,
+        )),
+    };
+    if !decompress {
+        return Ok(read);
+    }
+
+    let (magic, compression) = sniff(&mut read).map_err(InputError::Read)?;
+    Ok(decoder(compression, Cursor::new(magic).chain(read))
+        .map_err(InputError::Read)?)
+}
+
+// Plain concatenation skips line handling entirely. io::copy from a File
+// to the locked stdout lets Linux use copy_file_range or sendfile when
+// both ends allow it, and otherwise copies through the large BufWriter
+// buffer. The first block is read separately, so inputs that cannot be
+// read at all, such as a directory, fail there and only skip this file.
+fn copy_plain(
+    filename: &str,
+    decompress: bool,
+    out: &mut StdoutLock,
+) -> MyResult<()> {
     match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+        "-" => copy_raw(io::stdin().lock(), false, decompress, out),
+        _ => {
+            let file = File::open(filename).map_err(InputError::Open)?;
+            let regular = file.metadata().is_ok_and(|m| m.is_file());
+            copy_raw(file, regular, decompress, out)
+        }
+    }
+}
+
+fn copy_raw<R: Read + 'static>(
+    read: R,
+    regular: bool,
+    decompress: bool,
+    out: &mut StdoutLock,
+) -> MyResult<()> {
+    let mut out = BufWriter::with_capacity(COPY_BUF_SIZE, out);
+    let mut read = BufReader::with_capacity(COPY_BUF_SIZE, read);
+    read.fill_buf().map_err(InputError::Read)?;
+    if decompress {
+        let (magic, compression) = sniff(&mut read).map_err(InputError::Read)?;
+        if compression.is_some() {
+            // Nothing to gain from io::copy once data has to be decoded,
+            // and a corrupt archive is an input error like any other.
+            let read = Cursor::new(magic).chain(read);
+            let mut read = decoder(compression, read).map_err(InputError::Read)?;
+            copy_input(&mut read, &mut out)?;
+            out.flush()?;
+            return Ok(());
+        }
+        out.write_all(&magic)?;
+    }
+    copy_rest(&mut read, regular, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+// io::copy does not say which side failed. Wrapping a regular file would
+// lose the kernel copy, so instead it is read again after an error: a
+// failing input fails again, while after a failed write it still reads.
+// Reading again could block on anything else, such as a pipe, so there
+// the reader is wrapped to mark its own errors.
+fn copy_rest<R: Read>(
+    read: &mut BufReader<R>,
+    regular: bool,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    if !regular {
+        return match io::copy(&mut MarkErrors(read), out) {
+            Err(err) if err.get_ref().is_some_and(|e| e.is::<InputError>()) => {
+                Err(err.into_inner().unwrap_or_else(|| "read error".into()))
+            }
+            result => Ok(result.map(drop)?),
+        };
+    }
+    match io::copy(read, out) {
+        Err(err) if read.fill_buf().is_err() => Err(InputError::Read(err).into()),
+        result => Ok(result.map(drop)?),
+    }
+}
+
+// Marks errors from the reader as input errors, keeping their kind so that
+// io::copy still retries when interrupted.
+struct MarkErrors<R>(R);
+
+impl<R: Read> Read for MarkErrors<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0
+            .read(buf)
+            .map_err(|err| io::Error::new(err.kind(), InputError::Read(err)))
+    }
+}
+
+// Copies everything from `read`, reporting read errors as input errors.
+fn copy_input(read: &mut impl BufRead, out: &mut impl Write) -> MyResult<()> {
+    loop {
+        let buf = match read.fill_buf() {
+            Ok(buf) => buf,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(InputError::Read(err).into()),
+        };
+        if buf.is_empty() {
+            return Ok(());
+        }
+        out.write_all(buf)?;
+        let len = buf.len();
+        read.consume(len);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    const MAGIC_LEN: usize = 10;
+
+    const ALL: [Compression; 4] = [
+        Compression::Gzip,
+        Compression::Bzip2,
+        Compression::Xz,
+        Compression::Zstd,
+    ];
+
+    // The bytes allowed at each position of the magic. A bzip2 stream
+    // starts with its block size digit and then the first block's magic,
+    // so "BZh" alone is not mistaken for one.
+    fn signature(self) -> &'static [&'static [u8]] {
+        match self {
+            Compression::Gzip => &[b"\x1f", b"\x8b"],
+            Compression::Bzip2 => &[
+                b"B", b"Z", b"h", b"123456789", b"1", b"A", b"Y", b"&", b"S", b"Y",
+            ],
+            Compression::Xz => &[b"\xfd", b"7", b"z", b"X", b"Z", b"\x00"],
+            Compression::Zstd => &[b"\x28", b"\xb5", b"\x2f", b"\xfd"],
+        }
+    }
+
+    // Whether `bytes` fit the start of the signature, however short.
+    fn agrees(self, bytes: &[u8]) -> bool {
+        self.signature()
+            .iter()
+            .zip(bytes)
+            .all(|(allowed, byte)| allowed.contains(byte))
+    }
+
+    fn detect(magic: &[u8]) -> Option<Compression> {
+        Compression::ALL.into_iter().find(|compression| {
+            magic.len() >= compression.signature().len() && compression.agrees(magic)
+        })
+    }
+
+    // Whether more bytes could still make `magic` a signature.
+    fn may_follow(magic: &[u8]) -> bool {
+        Compression::ALL.into_iter().any(|compression| {
+            magic.len() < compression.signature().len() && compression.agrees(magic)
+        })
+    }
+}
+
+// Sniffs the magic bytes rather than trusting the file extension, so
+// compressed data on stdin is handled too. The bytes consumed while
+// sniffing are returned so they can be put back in front of the stream.
+// Only as much is read as could still be a signature, so a short first
+// line on a pipe is passed on at once.
+fn sniff(read: &mut impl BufRead) -> io::Result<(Vec<u8>, Option<Compression>)> {
+    let mut magic = Vec::new();
+    loop {
+        if let Some(compression) = Compression::detect(&magic) {
+            return Ok((magic, Some(compression)));
+        }
+        if !Compression::may_follow(&magic) {
+            return Ok((magic, None));
+        }
+        let buf = read.fill_buf()?;
+        if buf.is_empty() {
+            return Ok((magic, None));
+        }
+        let len = buf.len().min(Compression::MAGIC_LEN - magic.len());
+        magic.extend_from_slice(&buf[..len]);
+        read.consume(len);
+    }
+}
+
+fn decoder(
+    compression: Option<Compression>,
+    read: impl BufRead + 'static,
+) -> io::Result<Box<dyn BufRead>> {
+    Ok(match compression {
+        None => Box::new(read),
+        Some(Compression::Gzip) => Box::new(BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(read),
+        )),
+        Some(Compression::Bzip2) => Box::new(BufReader::new(
+            bzip2::bufread::MultiBzDecoder::new(read),
+        )),
+        Some(Compression::Xz) => Box::new(BufReader::new(
+            xz2::bufread::XzDecoder::new_multi_decoder(read),
+        )),
+        Some(Compression::Zstd) => Box::new(BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(read)?,
+        )),
+    })
+}
+
+// An error from the input side, which only skips the current file; write
+// errors still abort the whole run.
+#[derive(Debug)]
+enum InputError {
+    Open(io::Error),
+    Read(io::Error),
+    Include(String),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::Open(err) | InputError::Read(err) => err.fmt(f),
+            InputError::Include(msg) => msg.fmt(f),
+        }
+    }
+}
+
+impl Error for InputError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        copy_rest, run_to_writer, sniff, transform_lines, transformers, Compression,
+        Config, InputError,
+    };
+    use clap::Parser;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::io::{self, BufReader, Cursor, Read, Write};
+
+    fn cat_bytes(args: &[&str], input: &[u8]) -> Vec<u8> {
+        let config = Config::parse_from(["catr"].iter().chain(args).chain(&["-"]));
+        let mut out = Vec::new();
+        transform_lines(Cursor::new(input), &mut transformers(&config), &mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_transformers() {
+        let config = |args: &[&str]| {
+            Config::parse_from(["catr"].iter().chain(args).chain(&["-"]))
+        };
+        assert_eq!(transformers(&config(&[])).len(), 0);
+        assert_eq!(transformers(&config(&["-n"])).len(), 1);
+        assert_eq!(transformers(&config(&["-A"])).len(), 3);
+        assert_eq!(transformers(&config(&["-s", "-b", "-e"])).len(), 4);
+    }
+
+    #[test]
+    fn test_run_to_writer() {
+        let config = Config::parse_from([
+            "catr",
+            "-n",
+            "tests/inputs/fox.txt",
+            "tests/inputs/spiders.txt",
+            "tests/inputs/the-bustle.txt",
+        ]);
+        let mut out = Vec::new();
+        assert!(run_to_writer(&config, &mut out).is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            fs::read_to_string("tests/expected/all.n.out").unwrap()
+        );
+
+        let config = Config::parse_from(["catr", "tests/inputs/the-bustle.txt.gz"]);
+        let mut out = Vec::new();
+        assert!(run_to_writer(&config, &mut out).is_ok());
+        assert_eq!(out, fs::read("tests/inputs/the-bustle.txt").unwrap());
+    }
+
+    #[test]
+    fn test_cat_is_byte_exact() {
+        let input = b"caf\xe9\r\n\xff\xfe\n\nno newline";
+        assert_eq!(cat_bytes(&[], input), input);
+    }
+
+    #[test]
+    fn test_cat_numbers_byte_lines() {
+        let input = b"a\xff\r\n\nlast";
+        assert_eq!(
+            cat_bytes(&["-n"], input),
+            b"     1\ta\xff\r\n     2\t\n     3\tlast"
+        );
+        assert_eq!(
+            cat_bytes(&["-b"], input),
+            b"     1\ta\xff\r\n\n     2\tlast"
+        );
+    }
+
+    #[test]
+    fn test_cat_show_all() {
+        let input = b"a\tb\r\n\xe9\n\nend";
+        assert_eq!(cat_bytes(&["-A"], input), b"a^Ib^M$\nM-i$\n$\nend");
+        assert_eq!(cat_bytes(&["-E"], input), b"a\tb^M$\n\xe9$\n$\nend");
+        assert_eq!(cat_bytes(&["-t"], input), b"a^Ib^M\nM-i\n\nend");
+        assert_eq!(
+            cat_bytes(&["-b", "-e"], input),
+            b"     1\ta\tb^M$\n     2\tM-i$\n$\n     3\tend"
+        );
+    }
+
+    #[test]
+    fn test_cat_squeeze_blank() {
+        let input = b"a\n\n\n\nb\n\r\n\r\n\n\n";
+        assert_eq!(cat_bytes(&["-s"], input), b"a\n\nb\n\r\n\r\n\n");
+        assert_eq!(
+            cat_bytes(&["-s", "-n"], input),
+            b"     1\ta\n     2\t\n     3\tb\n     4\t\r\n     5\t\r\n     6\t\n"
+        );
+        assert_eq!(
+            cat_bytes(&["-s", "-b"], input),
+            b"     1\ta\n\n     2\tb\n     3\t\r\n     4\t\r\n\n"
+        );
+    }
+
+    #[test]
+    fn test_cat_number_start_increment() {
+        assert_eq!(
+            cat_bytes(
+                &[
+                    "-b",
+                    "--number-start",
+                    "10",
+                    "--number-increment",
+                    "5",
+                    "--number-separator",
+                    ": ",
+                ],
+                b"a\n\nb\n"
+            ),
+            b"    10: a\n\n    15: b\n"
+        );
+    }
+
+    #[test]
+    fn test_cat_number_overflow() {
+        assert_eq!(
+            cat_bytes(
+                &["-n", "--number-start", "9223372036854775806", "--number-width", "1"],
+                b"a\nb\nc\n"
+            ),
+            b"9223372036854775806\ta\n9223372036854775807\tb\n9223372036854775807\tc\n"
+        );
+        assert_eq!(
+            cat_bytes(
+                &[
+                    "-n",
+                    "--number-start=-9223372036854775807",
+                    "--number-increment=-5",
+                    "--number-width=1",
+                ],
+                b"a\nb\n"
+            ),
+            b"-9223372036854775807\ta\n-9223372036854775808\tb\n"
+        );
+    }
+
+    #[test]
+    fn test_compression_detect() {
+        assert_eq!(
+            Compression::detect(&[0x1f, 0x8b, 0x08, 0, 0, 0]),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::detect(b"BZh91AY&SY"),
+            Some(Compression::Bzip2)
+        );
+        assert_eq!(
+            Compression::detect(b"\xfd7zXZ\x00"),
+            Some(Compression::Xz)
+        );
+        assert_eq!(
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0, 0]),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::detect(b"\xfd7zXZ"), None);
+        assert_eq!(Compression::detect(b"\x1f"), None);
+        assert_eq!(Compression::detect(b""), None);
+        assert_eq!(Compression::detect(b"plain text"), None);
+        assert_eq!(Compression::detect(b"BZh is a plain note"), None);
+        assert_eq!(Compression::detect(b"BZh01AY&SY"), None);
+        assert_eq!(Compression::detect(b"BZh91AY&S"), None);
+    }
+
+    // Reads the bytes it holds, then fails every time.
+    struct Failing(&'static [u8]);
+
+    impl Read for Failing {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.read(buf)? {
+                0 => Err(io::Error::other("disk gone")),
+                len => Ok(len),
+            }
+        }
+    }
+
+    struct Full;
+
+    impl Write for Full {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_copy_rest_errors() {
+        for regular in [false, true] {
+            let mut out = Vec::new();
+            let mut read = BufReader::with_capacity(2, Failing(b"abc"));
+            let err = copy_rest(&mut read, regular, &mut out).unwrap_err();
+            assert!(matches!(err.downcast_ref(), Some(InputError::Read(_))), "{}", err);
+            assert_eq!(err.to_string(), "disk gone");
+            assert_eq!(out, b"abc");
+
+            let mut read = BufReader::new(&b"abc"[..]);
+            let err = copy_rest(&mut read, regular, &mut Full).unwrap_err();
+            assert!(err.downcast_ref::<InputError>().is_none(), "{}", err);
+            assert_eq!(err.to_string(), "disk full");
+        }
+    }
+
+    // Stands for a pipe with nothing more to read yet.
+    struct Blocked;
+
+    impl Read for Blocked {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            panic!("read past what was available");
+        }
+    }
+
+    #[test]
+    fn test_sniff() {
+        let (magic, compression) = sniff(&mut &b"BZh91AY&SY..."[..]).unwrap();
+        assert_eq!(magic, b"BZh91AY&SY");
+        assert_eq!(compression, Some(Compression::Bzip2));
+        let (magic, compression) = sniff(&mut &b"BZh is a plain note"[..]).unwrap();
+        assert_eq!(magic, b"BZh is a p");
+        assert_eq!(compression, None);
+
+        // A byte at a time, to see that no more is read than needed.
+        let slow = |input: &'static [u8]| BufReader::with_capacity(1, input.chain(Blocked));
+        let (magic, compression) = sniff(&mut slow(b"hi\n")).unwrap();
+        assert_eq!((magic, compression), (b"h".to_vec(), None));
+        let (magic, compression) = sniff(&mut slow(b"BZh is")).unwrap();
+        assert_eq!((magic, compression), (b"BZh ".to_vec(), None));
+        let (magic, compression) = sniff(&mut slow(b"\x1f\x8b")).unwrap();
+        assert_eq!((magic, compression), (b"\x1f\x8b".to_vec(), Some(Compression::Gzip)));
+        let (magic, compression) = sniff(&mut &b"\x1f"[..]).unwrap();
+        assert_eq!((magic, compression), (b"\x1f".to_vec(), None));
     }
 }
@@ -0,0 +1,183 @@
+use std::io::{self, BufRead};
+
+/// A START:END selection. Positions are 1-based and inclusive; negative
+/// positions count from the end (-1 is the last line or byte) and a
+/// missing position leaves that side open.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Span {
+    start: Option<i64>,
+    end: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Unit {
+    Lines,
+    Bytes,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Selection {
+    pub unit: Unit,
+    pub span: Span,
+}
+
+// cargo test select::tests::test_parse_span
+pub(crate) fn parse_span(val: &str) -> Result<Span, String> {
+    let invalid = |why: &str| format!("illegal range \"{}\": {}", val, why);
+    let parse_pos = |pos: &str| match pos {
+        "" => Ok(None),
+        _ => match pos.parse::<i64>() {
+            Ok(0) => Err(invalid("positions start at 1")),
+            Ok(n) if !pos.starts_with('+') => Ok(Some(n)),
+            _ => Err(invalid("expected START:END")),
+        },
+    };
+
+    let (start, end) = val.split_once(':').ok_or_else(|| invalid("expected START:END"))?;
+    let span = Span {
+        start: parse_pos(start)?,
+        end: parse_pos(end)?,
+    };
+    if let (Some(start), Some(end)) = (span.start, span.end) {
+        if start.signum() == end.signum() && start > end {
+            return Err(invalid("START must not be after END"));
+        }
+    }
+    Ok(span)
+}
+
+impl Span {
+    // Only positions counted from the end need the total up front.
+    pub fn needs_total(&self) -> bool {
+        self.start.is_some_and(|n| n < 0) || self.end.is_some_and(|n| n < 0)
+    }
+
+    /// Turns the span into absolute 1-based inclusive bounds, given the
+    /// total number of lines or bytes when `needs_total` says it is needed.
+    pub fn resolve(&self, total: Option<u64>) -> (u64, u64) {
+        let total = total.unwrap_or(u64::MAX);
+        let absolute = |pos: i64| match pos {
+            n if n > 0 => n as u64,
+            n => total.saturating_add(1).saturating_sub(n.unsigned_abs()),
+        };
+        let first = self.start.map_or(1, absolute).max(1);
+        let last = self.end.map_or(u64::MAX, absolute);
+        (first, last)
+    }
+}
+
+// Counts what a Selection is measured in, for spans relative to the end.
+pub(crate) fn count_total(mut read: impl BufRead, unit: Unit) -> io::Result<u64> {
+    let mut total = 0;
+    let mut ends_with_newline = true;
+    loop {
+        let buf = read.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        total += match unit {
+            Unit::Bytes => buf.len() as u64,
+            Unit::Lines => buf.iter().filter(|&&b| b == b'\n').count() as u64,
+        };
+        ends_with_newline = buf.last() == Some(&b'\n');
+        let len = buf.len();
+        read.consume(len);
+    }
+    if unit == Unit::Lines && !ends_with_newline {
+        total += 1;
+    }
+    Ok(total)
+}
+
+// Cuts `bytes`, which start at the 0-based input `offset`, down to the
+// part inside the 1-based inclusive range first..=last. Returns false if
+// nothing is left.
+pub(crate) fn clip(bytes: &mut Vec<u8>, offset: u64, first: u64, last: u64) -> bool {
+    let len = bytes.len() as u64;
+    let from = first.saturating_sub(offset + 1).min(len);
+    let to = last.saturating_sub(offset).min(len);
+    if from >= to {
+        bytes.clear();
+        return false;
+    }
+    bytes.truncate(to as usize);
+    bytes.drain(..from as usize);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clip, count_total, parse_span, Span, Unit};
+    use pretty_assertions::assert_eq;
+    use std::io::Cursor;
+
+    fn span(start: Option<i64>, end: Option<i64>) -> Span {
+        Span { start, end }
+    }
+
+    #[test]
+    fn test_parse_span() {
+        assert_eq!(parse_span("1200:1350"), Ok(span(Some(1200), Some(1350))));
+        assert_eq!(parse_span("5:"), Ok(span(Some(5), None)));
+        assert_eq!(parse_span(":5"), Ok(span(None, Some(5))));
+        assert_eq!(parse_span(":"), Ok(span(None, None)));
+        assert_eq!(parse_span("-10:"), Ok(span(Some(-10), None)));
+        assert_eq!(parse_span("-10:-2"), Ok(span(Some(-10), Some(-2))));
+        assert_eq!(parse_span("3:-2"), Ok(span(Some(3), Some(-2))));
+        assert_eq!(parse_span("-3:2"), Ok(span(Some(-3), Some(2))));
+        assert_eq!(parse_span("7:7"), Ok(span(Some(7), Some(7))));
+
+        assert_eq!(
+            parse_span("0:5").unwrap_err(),
+            r#"illegal range "0:5": positions start at 1"#
+        );
+        assert_eq!(
+            parse_span("5:2").unwrap_err(),
+            r#"illegal range "5:2": START must not be after END"#
+        );
+        assert_eq!(
+            parse_span("-2:-5").unwrap_err(),
+            r#"illegal range "-2:-5": START must not be after END"#
+        );
+        assert!(parse_span("5").is_err());
+        assert!(parse_span("+5:").is_err());
+        assert!(parse_span("a:b").is_err());
+        assert!(parse_span("1:2:3").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(span(Some(2), Some(4)).resolve(None), (2, 4));
+        assert_eq!(span(None, None).resolve(None), (1, u64::MAX));
+        assert_eq!(span(Some(-3), None).resolve(Some(10)), (8, u64::MAX));
+        assert_eq!(span(Some(-30), None).resolve(Some(10)), (1, u64::MAX));
+        assert_eq!(span(None, Some(-2)).resolve(Some(10)), (1, 9));
+        assert_eq!(span(Some(2), Some(-20)).resolve(Some(10)), (2, 0));
+        assert!(!span(Some(2), None).needs_total());
+        assert!(span(Some(2), Some(-1)).needs_total());
+    }
+
+    #[test]
+    fn test_count_total() {
+        let count = |text: &str, unit| count_total(Cursor::new(text), unit).unwrap();
+        assert_eq!(count("", Unit::Lines), 0);
+        assert_eq!(count("a\nb\n", Unit::Lines), 2);
+        assert_eq!(count("a\nb", Unit::Lines), 2);
+        assert_eq!(count("a\nb", Unit::Bytes), 3);
+    }
+
+    #[test]
+    fn test_clip() {
+        let clipped = |offset, first, last| {
+            let mut bytes = b"abcd\n".to_vec();
+            clip(&mut bytes, offset, first, last).then_some(bytes)
+        };
+        assert_eq!(clipped(0, 1, u64::MAX), Some(b"abcd\n".to_vec()));
+        assert_eq!(clipped(0, 2, 3), Some(b"bc".to_vec()));
+        assert_eq!(clipped(10, 12, 13), Some(b"bc".to_vec()));
+        assert_eq!(clipped(10, 1, 11), Some(b"a".to_vec()));
+        assert_eq!(clipped(10, 15, 20), Some(b"\n".to_vec()));
+        assert_eq!(clipped(10, 16, 20), None);
+        assert_eq!(clipped(10, 1, 10), None);
+    }
+}
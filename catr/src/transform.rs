@@ -0,0 +1,383 @@
+use clap::ValueEnum;
+use std::io::{self, BufRead};
+use std::mem;
+
+/// One input line on its way through a chain of [`LineTransformer`]s.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Line {
+    /// Written before the line, e.g. its number
+    pub prefix: Vec<u8>,
+    /// The line as read, including its terminator if it had one
+    pub bytes: Vec<u8>,
+    /// Whether the line as read was empty
+    pub blank: bool,
+}
+
+impl Line {
+    pub fn new(bytes: Vec<u8>) -> Line {
+        let blank = bytes == b"\n";
+        Line {
+            prefix: Vec::new(),
+            bytes,
+            blank,
+        }
+    }
+
+    // Reads the next line into `self`, reusing its buffers.
+    pub(crate) fn read_from(&mut self, read: &mut impl BufRead) -> io::Result<usize> {
+        self.prefix.clear();
+        self.bytes.clear();
+        let bytes_read = read.read_until(b'\n', &mut self.bytes)?;
+        self.blank = self.bytes == b"\n";
+        Ok(bytes_read)
+    }
+
+    // Splits off the trailing newline, if any.
+    fn body(&self) -> (&[u8], bool) {
+        match self.bytes.strip_suffix(b"\n") {
+            Some(body) => (body, true),
+            None => (&self.bytes, false),
+        }
+    }
+}
+
+/// A step applied to every line, such as numbering or showing tabs.
+pub trait LineTransformer {
+    /// Rewrites `line` in place. Returns false to drop the line.
+    fn transform(&mut self, line: &mut Line) -> bool;
+
+    /// Called before each input file unless numbering continues across
+    /// files; resets line numbers.
+    fn start_file(&mut self) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum NumberFormat {
+    /// Left justified
+    Ln,
+    /// Right justified
+    Rn,
+    /// Right justified, zero padded
+    Rz,
+}
+
+/// The widest line number field, which is more than any `i64` needs.
+pub const MAX_NUMBER_WIDTH: usize = 64;
+
+/// How line numbers are counted and formatted. A width beyond
+/// `MAX_NUMBER_WIDTH` is treated as `MAX_NUMBER_WIDTH`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberStyle {
+    pub width: usize,
+    pub separator: String,
+    pub start: i64,
+    pub increment: i64,
+    pub format: NumberFormat,
+}
+
+impl Default for NumberStyle {
+    fn default() -> Self {
+        NumberStyle {
+            width: 6,
+            separator: "\t".to_string(),
+            start: 1,
+            increment: 1,
+            format: NumberFormat::Rn,
+        }
+    }
+}
+
+impl NumberStyle {
+    pub fn format(&self, number: i64) -> String {
+        let width = self.width.min(MAX_NUMBER_WIDTH);
+        match self.format {
+            NumberFormat::Ln => format!("{:<width$}", number),
+            NumberFormat::Rn => format!("{:>width$}", number),
+            NumberFormat::Rz => format!("{:0width$}", number),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Counter {
+    style: NumberStyle,
+    next: i64,
+}
+
+impl Counter {
+    fn new(style: NumberStyle) -> Counter {
+        let next = style.start;
+        Counter { style, next }
+    }
+
+    fn number(&mut self, line: &mut Line) {
+        line.prefix.extend_from_slice(self.style.format(self.next).as_bytes());
+        line.prefix.extend_from_slice(self.style.separator.as_bytes());
+        // Numbers stop at the ends of the i64 range rather than wrap.
+        self.next = self.next.saturating_add(self.style.increment);
+    }
+
+    fn reset(&mut self) {
+        self.next = self.style.start;
+    }
+}
+
+/// Numbers every line (-n).
+#[derive(Debug, Clone)]
+pub struct NumberLines(Counter);
+
+impl NumberLines {
+    pub fn new(style: NumberStyle) -> NumberLines {
+        NumberLines(Counter::new(style))
+    }
+}
+
+impl LineTransformer for NumberLines {
+    fn transform(&mut self, line: &mut Line) -> bool {
+        self.0.number(line);
+        true
+    }
+
+    fn start_file(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// Numbers only non-blank lines (-b).
+#[derive(Debug, Clone)]
+pub struct NumberNonblank(Counter);
+
+impl NumberNonblank {
+    pub fn new(style: NumberStyle) -> NumberNonblank {
+        NumberNonblank(Counter::new(style))
+    }
+}
+
+impl LineTransformer for NumberNonblank {
+    fn transform(&mut self, line: &mut Line) -> bool {
+        if !line.blank {
+            self.0.number(line);
+        }
+        true
+    }
+
+    fn start_file(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// Drops blank lines that follow another blank line (-s).
+#[derive(Debug, Clone, Default)]
+pub struct SqueezeBlank {
+    prev_blank: bool,
+}
+
+impl LineTransformer for SqueezeBlank {
+    fn transform(&mut self, line: &mut Line) -> bool {
+        let keep = !(line.blank && self.prev_blank);
+        self.prev_blank = line.blank;
+        keep
+    }
+
+    // Like GNU cat, a run of blank lines is squeezed even when it spans
+    // files, so nothing is reset here.
+}
+
+/// Marks the end of each line with $ (-E). Like GNU cat, a CR before the
+/// newline is shown as ^M.
+#[derive(Debug, Clone, Default)]
+pub struct ShowEnds;
+
+impl LineTransformer for ShowEnds {
+    fn transform(&mut self, line: &mut Line) -> bool {
+        if line.bytes.last() == Some(&b'\n') {
+            line.bytes.pop();
+            if line.bytes.last() == Some(&b'\r') {
+                line.bytes.pop();
+                line.bytes.extend_from_slice(b"^M");
+            }
+            line.bytes.extend_from_slice(b"$\n");
+        }
+        true
+    }
+}
+
+/// Shows TAB characters as ^I (-T).
+#[derive(Debug, Clone, Default)]
+pub struct ShowTabs {
+    scratch: Vec<u8>,
+}
+
+impl LineTransformer for ShowTabs {
+    fn transform(&mut self, line: &mut Line) -> bool {
+        if line.bytes.contains(&b'\t') {
+            rewrite(line, &mut self.scratch, |byte, shown| match byte {
+                b'\t' => shown.extend_from_slice(b"^I"),
+                _ => shown.push(byte),
+            });
+        }
+        true
+    }
+}
+
+/// Uses ^ and M- notation for control and high bytes, except for the
+/// newline and TAB (-v).
+#[derive(Debug, Clone, Default)]
+pub struct ShowNonprinting {
+    scratch: Vec<u8>,
+}
+
+impl LineTransformer for ShowNonprinting {
+    fn transform(&mut self, line: &mut Line) -> bool {
+        rewrite(line, &mut self.scratch, |byte, shown| match byte {
+            b'\t' => shown.push(byte),
+            _ => push_nonprinting(byte, shown),
+        });
+        true
+    }
+}
+
+// Maps every byte before the trailing newline through `show`, reusing
+// `scratch` so that no allocation is needed per line.
+fn rewrite(line: &mut Line, scratch: &mut Vec<u8>, show: impl Fn(u8, &mut Vec<u8>)) {
+    scratch.clear();
+    let (body, newline) = line.body();
+    for &byte in body {
+        show(byte, scratch);
+    }
+    if newline {
+        scratch.push(b'\n');
+    }
+    mem::swap(&mut line.bytes, scratch);
+}
+
+fn push_nonprinting(byte: u8, shown: &mut Vec<u8>) {
+    let byte = if byte >= 128 {
+        shown.extend_from_slice(b"M-");
+        byte - 128
+    } else {
+        byte
+    };
+    match byte {
+        0..=31 => shown.extend_from_slice(&[b'^', byte + 64]),
+        127 => shown.extend_from_slice(b"^?"),
+        _ => shown.push(byte),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        push_nonprinting, Line, LineTransformer, NumberFormat, NumberLines,
+        NumberNonblank, NumberStyle, ShowEnds, ShowNonprinting, ShowTabs,
+        SqueezeBlank,
+    };
+    use pretty_assertions::assert_eq;
+
+    fn apply(transformer: &mut impl LineTransformer, input: &[u8]) -> Option<Vec<u8>> {
+        let mut line = Line::new(input.to_vec());
+        transformer.transform(&mut line).then(|| {
+            let mut shown = line.prefix;
+            shown.extend(line.bytes);
+            shown
+        })
+    }
+
+    #[test]
+    fn test_line_new() {
+        assert!(Line::new(b"\n".to_vec()).blank);
+        assert!(!Line::new(b"\r\n".to_vec()).blank);
+        assert!(!Line::new(b"".to_vec()).blank);
+        assert!(!Line::new(b" \n".to_vec()).blank);
+        assert!(!Line::new(b"a\n".to_vec()).blank);
+    }
+
+    #[test]
+    fn test_push_nonprinting() {
+        let show = |byte| {
+            let mut shown = Vec::new();
+            push_nonprinting(byte, &mut shown);
+            String::from_utf8(shown).unwrap()
+        };
+        assert_eq!(show(b'a'), "a");
+        assert_eq!(show(0), "^@");
+        assert_eq!(show(b'\r'), "^M");
+        assert_eq!(show(127), "^?");
+        assert_eq!(show(0x80), "M-^@");
+        assert_eq!(show(0xe9), "M-i");
+        assert_eq!(show(0xff), "M-^?");
+    }
+
+    #[test]
+    fn test_number_style_format() {
+        let style = |format, width| NumberStyle {
+            format,
+            width,
+            ..Default::default()
+        };
+        assert_eq!(NumberStyle::default().format(7), "     7");
+        assert_eq!(style(NumberFormat::Ln, 6).format(7), "7     ");
+        assert_eq!(style(NumberFormat::Rz, 6).format(7), "000007");
+        assert_eq!(style(NumberFormat::Rz, 3).format(-7), "-07");
+        assert_eq!(style(NumberFormat::Rn, 2).format(1234), "1234");
+    }
+
+    #[test]
+    fn test_number_lines() {
+        let mut numberer = NumberLines::new(NumberStyle {
+            separator: ": ".to_string(),
+            start: 10,
+            increment: 5,
+            ..Default::default()
+        });
+        assert_eq!(apply(&mut numberer, b"a\n").unwrap(), b"    10: a\n");
+        assert_eq!(apply(&mut numberer, b"\n").unwrap(), b"    15: \n");
+        numberer.start_file();
+        assert_eq!(apply(&mut numberer, b"b").unwrap(), b"    10: b");
+    }
+
+    #[test]
+    fn test_number_nonblank() {
+        let mut numberer = NumberNonblank::new(NumberStyle::default());
+        assert_eq!(apply(&mut numberer, b"a\n").unwrap(), b"     1\ta\n");
+        assert_eq!(apply(&mut numberer, b"\n").unwrap(), b"\n");
+        assert_eq!(apply(&mut numberer, b"\r\n").unwrap(), b"     2\t\r\n");
+    }
+
+    #[test]
+    fn test_squeeze_blank() {
+        let mut squeeze = SqueezeBlank::default();
+        assert!(apply(&mut squeeze, b"\n").is_some());
+        assert!(apply(&mut squeeze, b"\n").is_none());
+        assert!(apply(&mut squeeze, b"a\n").is_some());
+        assert!(apply(&mut squeeze, b"\n").is_some());
+        squeeze.start_file();
+        assert!(apply(&mut squeeze, b"\n").is_none());
+    }
+
+    #[test]
+    fn test_show_ends() {
+        assert_eq!(apply(&mut ShowEnds, b"a\tb\n").unwrap(), b"a\tb$\n");
+        assert_eq!(apply(&mut ShowEnds, b"a\r\n").unwrap(), b"a^M$\n");
+        assert_eq!(apply(&mut ShowEnds, b"\r\r\n").unwrap(), b"\r^M$\n");
+        assert_eq!(apply(&mut ShowEnds, b"\n").unwrap(), b"$\n");
+        assert_eq!(apply(&mut ShowEnds, b"end").unwrap(), b"end");
+    }
+
+    #[test]
+    fn test_show_tabs() {
+        let mut show = ShowTabs::default();
+        assert_eq!(apply(&mut show, b"a\tb\r\n").unwrap(), b"a^Ib\r\n");
+        assert_eq!(apply(&mut show, b"\t").unwrap(), b"^I");
+    }
+
+    #[test]
+    fn test_show_nonprinting() {
+        let mut show = ShowNonprinting::default();
+        assert_eq!(
+            apply(&mut show, b"a\tb\r\n").unwrap(),
+            b"a\tb^M\n"
+        );
+        assert_eq!(apply(&mut show, b"\xe9\x01").unwrap(), b"M-i^A");
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use assert_cmd::cargo::CommandCargoExt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const PRG: &str = "catr";
+const INPUT_SIZE: usize = 128 * 1024 * 1024;
+
+// --------------------------------------------------
+fn gen_input(path: &Path) -> Result<()> {
+    // No blank lines, so -s takes the line-based path without changing the
+    // output and both runs can be compared byte for byte.
+    let mut file = BufWriter::new(File::create(path)?);
+    let mut written = 0;
+    let mut line_num = 0;
+    while written < INPUT_SIZE {
+        let line = format!("{line_num:>10} The quick brown fox jumps over the lazy dog.\n");
+        file.write_all(line.as_bytes())?;
+        written += line.len();
+        line_num += 1;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// --------------------------------------------------
+fn time_run(args: &[&str], out_path: &Path) -> Result<Duration> {
+    let start = Instant::now();
+    let status = Command::cargo_bin(PRG)?
+        .args(args)
+        .stdout(File::create(out_path)?)
+        .status()?;
+    let elapsed = start.elapsed();
+    assert!(status.success());
+    Ok(elapsed)
+}
+
+// --------------------------------------------------
+fn mib_per_sec(elapsed: Duration) -> f64 {
+    INPUT_SIZE as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64()
+}
+
+// --------------------------------------------------
+// cargo test --release --test bench -- --ignored --nocapture
+#[test]
+#[ignore]
+fn plain_copy_throughput() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let input = dir.path().join("input.txt");
+    gen_input(&input)?;
+    let input = input.to_str().unwrap();
+
+    let fast_out = dir.path().join("fast.out");
+    let line_out = dir.path().join("line.out");
+    let fast = time_run(&[input], &fast_out)?;
+    let line = time_run(&["-s", input], &line_out)?;
+
+    assert!(fs::read(&fast_out)? == fs::read(&line_out)?);
+    println!(
+        "plain copy: {:>8.1} MiB/s ({:?})",
+        mib_per_sec(fast),
+        fast
+    );
+    println!(
+        "line-based: {:>8.1} MiB/s ({:?})",
+        mib_per_sec(line),
+        line
+    );
+    Ok(())
+}
@@ -10,6 +10,8 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const SPIDERS: &str = "tests/inputs/spiders.txt";
 const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+const LATIN1: &str = "tests/inputs/latin1-crlf.txt";
+const BLANKS: &str = "tests/inputs/blanks.txt";
 
 // --------------------------------------------------
 #[test]
@@ -46,11 +48,29 @@ fn skips_bad_file() -> Result<()> {
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn continues_past_bad_file() -> Result<()> {
+    let bad = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/all.out")?;
+    Command::cargo_bin(PRG)?
+        .args([FOX, &bad, SPIDERS, "tests/inputs", BUSTLE])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(predicate::str::is_match(format!(
+            "Failed to open {bad}: .* [(]os error 2[)]"
+        ))?)
+        .stderr(predicate::str::contains("Failed to read tests/inputs: "))
+        .stderr(predicate::str::contains("2 inputs could not be read"));
+    Ok(())
+}
+
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> Result<()> {
     let expected = fs::read_to_string(expected_file)?;
@@ -63,6 +83,16 @@ fn run(args: &[&str], expected_file: &str) -> Result<()> {
     Ok(())
 }
 
+// --------------------------------------------------
+fn run_bytes(args: &[&str], expected_file: &str) -> Result<()> {
+    let expected = fs::read(expected_file)?;
+    let output = Command::cargo_bin(PRG)?.args(args).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+
+    Ok(())
+}
+
 // --------------------------------------------------
 fn run_stdin(
     input_file: &str,
@@ -201,3 +231,511 @@ fn all_n() -> Result<()> {
 fn all_b() -> Result<()> {
     run(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn latin1() -> Result<()> {
+    run_bytes(&[LATIN1], "tests/expected/latin1-crlf.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_n() -> Result<()> {
+    run_bytes(&["-n", LATIN1], "tests/expected/latin1-crlf.txt.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_b() -> Result<()> {
+    run_bytes(&["-b", LATIN1], "tests/expected/latin1-crlf.txt.b.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_stdin() -> Result<()> {
+    let input = fs::read(LATIN1)?;
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input.clone())
+        .arg("-")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, input);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_show_all() -> Result<()> {
+    run(&["--show-all", BUSTLE], "tests/expected/the-bustle.txt.A.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_show_all() -> Result<()> {
+    run_bytes(&["-A", LATIN1], "tests/expected/latin1-crlf.txt.A.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_show_ends() -> Result<()> {
+    run_bytes(&["-E", LATIN1], "tests/expected/latin1-crlf.txt.E.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_show_tabs() -> Result<()> {
+    run_bytes(&["-T", LATIN1], "tests/expected/latin1-crlf.txt.T.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_show_nonprinting() -> Result<()> {
+    run_bytes(&["-v", LATIN1], "tests/expected/latin1-crlf.txt.v.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_e() -> Result<()> {
+    run_bytes(&["-e", LATIN1], "tests/expected/latin1-crlf.txt.vE.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_t() -> Result<()> {
+    run_bytes(&["-t", LATIN1], "tests/expected/latin1-crlf.txt.vT.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_n_show_all() -> Result<()> {
+    run_bytes(&["-n", "-A", LATIN1], "tests/expected/latin1-crlf.txt.nA.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn blanks_s() -> Result<()> {
+    run(&["-s", BLANKS], "tests/expected/blanks.txt.s.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn blanks_sn() -> Result<()> {
+    run(&["--squeeze-blank", "-n", BLANKS], "tests/expected/blanks.txt.sn.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn blanks_sb() -> Result<()> {
+    run(&["-s", "-b", BLANKS], "tests/expected/blanks.txt.sb.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn blanks_s_across_files() -> Result<()> {
+    run(&["-s", BLANKS, BLANKS], "tests/expected/blanks.txt.twice.s.out")?;
+    run(
+        &["-s", "--continue-numbering", BLANKS, BLANKS],
+        "tests/expected/blanks.txt.twice.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn blanks_sn_across_files() -> Result<()> {
+    run(
+        &["-sn", "--continue-numbering", BLANKS, BLANKS],
+        "tests/expected/blanks.txt.twice.sn.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_n_continue_numbering() -> Result<()> {
+    run(
+        &["-n", "--continue-numbering", FOX, SPIDERS, BUSTLE],
+        "tests/expected/all.n.continue.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_b_continue_numbering() -> Result<()> {
+    run(
+        &["-b", "--continue-numbering", FOX, SPIDERS, BUSTLE],
+        "tests/expected/all.b.continue.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_gz_n() -> Result<()> {
+    run(
+        &["-n", "tests/inputs/the-bustle.txt.gz"],
+        "tests/expected/the-bustle.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_bz2_n() -> Result<()> {
+    run(
+        &["-n", "tests/inputs/the-bustle.txt.bz2"],
+        "tests/expected/the-bustle.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_xz_n() -> Result<()> {
+    run(
+        &["-n", "tests/inputs/the-bustle.txt.xz"],
+        "tests/expected/the-bustle.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_zst_n() -> Result<()> {
+    run(
+        &["-n", "tests/inputs/the-bustle.txt.zst"],
+        "tests/expected/the-bustle.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_gz_stdin_n() -> Result<()> {
+    let input = fs::read("tests/inputs/the-bustle.txt.gz")?;
+    let expected = fs::read_to_string("tests/expected/the-bustle.txt.n.stdin.out")?;
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .args(["-n", "-"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multi_member_gz() -> Result<()> {
+    run(
+        &["tests/inputs/fox-spiders.txt.gz"],
+        "tests/expected/fox-spiders.txt.gz.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn no_decompress() -> Result<()> {
+    run_bytes(
+        &["--no-decompress", "tests/inputs/the-bustle.txt.gz"],
+        "tests/inputs/the-bustle.txt.gz",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bzip2_lookalike_is_plain_text() -> Result<()> {
+    let note = "tests/inputs/bzh-note.txt";
+    Command::cargo_bin(PRG)?
+        .args([note, FOX])
+        .assert()
+        .success()
+        .stdout(fs::read_to_string(note)? + &fs::read_to_string(FOX)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn no_decompress_continues_past_directory() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--no-decompress", "tests/inputs", FOX])
+        .assert()
+        .failure()
+        .stdout(fs::read_to_string(FOX)?)
+        .stderr(predicate::str::contains("Failed to read tests/inputs: "))
+        .stderr(predicate::str::contains("1 input could not be read"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn continues_past_corrupt_archive() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/truncated.txt.gz", FOX])
+        .assert()
+        .failure()
+        .stdout(predicate::str::starts_with("The bustle in a house\n"))
+        .stdout(predicate::str::ends_with(fs::read_to_string(FOX)?))
+        .stderr(predicate::str::contains(
+            "Failed to read tests/inputs/truncated.txt.gz: ",
+        ))
+        .stderr(predicate::str::contains("1 input could not be read"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_n_number_format_rz() -> Result<()> {
+    run(
+        &[
+            "-n",
+            "--number-format=rz",
+            "--number-width=3",
+            "--number-separator=| ",
+            "--number-start=0",
+            "--number-increment=2",
+            BUSTLE,
+        ],
+        "tests/expected/the-bustle.txt.n.rz.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_b_number_format_ln() -> Result<()> {
+    run(
+        &[
+            "-b",
+            "--number-format",
+            "ln",
+            "--number-width",
+            "4",
+            "--number-separator",
+            " ",
+            BUSTLE,
+        ],
+        "tests/expected/the-bustle.txt.b.ln.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_number_width() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--number-width", "0", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value '0' for '--number-width <WIDTH>'",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_huge_number_width() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--number-width", "100000000000", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value '100000000000' for '--number-width <WIDTH>'",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn number_start_max_continue_numbering() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([
+            "-n",
+            "--number-start",
+            "9223372036854775807",
+            "--continue-numbering",
+            FOX,
+            SPIDERS,
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("9223372036854775807\tDon't worry"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_includes() -> Result<()> {
+    run(
+        &["--follow-includes", "tests/inputs/includes/main.conf"],
+        "tests/expected/includes.main.conf.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_includes_markers_n() -> Result<()> {
+    run(
+        &[
+            "--follow-includes",
+            "--include-markers",
+            "-n",
+            "tests/inputs/includes/main.conf",
+        ],
+        "tests/expected/includes.main.conf.markers.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_includes_custom_pattern() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([
+            "--follow-includes",
+            "--include-pattern",
+            r"^@import (\S+)$",
+            "tests/inputs/includes/custom.conf",
+        ])
+        .assert()
+        .success()
+        .stdout("x = 1\ntimeout = 30\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_includes_cycle() -> Result<()> {
+    let dir = "tests/inputs/includes";
+    Command::cargo_bin(PRG)?
+        .args(["--follow-includes", &format!("{dir}/cycle-a.conf"), FOX])
+        .assert()
+        .failure()
+        .stdout("a = 1\nb = 2\nc = 3\nThe quick brown fox jumps over the lazy dog.\n")
+        .stderr(predicate::str::contains(format!(
+            "Include cycle: {dir}/cycle-a.conf -> {dir}/cycle-b.conf \
+            -> {dir}/cycle-c.conf -> {dir}/cycle-b.conf"
+        )));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_includes_missing() -> Result<()> {
+    let dir = "tests/inputs/includes";
+    Command::cargo_bin(PRG)?
+        .args(["--follow-includes", &format!("{dir}/missing.conf")])
+        .assert()
+        .failure()
+        .stdout("before\n")
+        .stderr(predicate::str::is_match(format!(
+            "Failed to include {dir}/nope.conf from {dir}/missing.conf:2: \
+            .* [(]os error 2[)]"
+        ))?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_include_pattern_without_group() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--follow-includes", "--include-pattern", "^@import", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "pattern must capture the included path in a group",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_lines_n() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--lines", "6:8", BUSTLE])
+        .assert()
+        .success()
+        .stdout(
+            "     6\tThe sweeping up the heart,\n     \
+            7\tAnd putting love away\n     \
+            8\tWe shall not want to use again\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_lines_from_end_b() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-b", "--lines", "-6:-4", BUSTLE])
+        .assert()
+        .success()
+        .stdout("     4\tEnacted upon earth,—\n\n     5\tThe sweeping up the heart,\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_lines_from_end_stdin() -> Result<()> {
+    let input = fs::read_to_string(BUSTLE)?;
+    Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .args(["-n", "--lines=-2:", "-"])
+        .assert()
+        .success()
+        .stdout("     8\tWe shall not want to use again\n     9\tUntil eternity.\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_lines_open_start() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--lines", ":2", BUSTLE, FOX])
+        .assert()
+        .success()
+        .stdout(
+            "The bustle in a house\nThe morning after death\n\
+            The quick brown fox jumps over the lazy dog.\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_bytes() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--bytes", "5:20", BUSTLE])
+        .assert()
+        .success()
+        .stdout("bustle in a hous");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_bytes_from_end_n() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--bytes", "-20:", BUSTLE])
+        .assert()
+        .success()
+        .stdout("     8\tain\n     9\tUntil eternity.\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_bytes_are_raw() -> Result<()> {
+    let output = Command::cargo_bin(PRG)?
+        .args(["--bytes", "4:4", LATIN1])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"\xe9");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_lines_range() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--lines", "0:3", BUSTLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            r#"illegal range "0:3": positions start at 1"#,
+        ));
+    Ok(())
+}
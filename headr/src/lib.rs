@@ -1,10 +1,19 @@
 use clap::Parser;
+use std::collections::VecDeque;
 use std::fs::File;
 use std::error::Error;
-use std::io::{self, BufRead, BufReader};
+use std::fmt;
+use regex::bytes::Regex;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use unicode_segmentation::UnicodeSegmentation;
+
+mod summary;
+mod table;
 
 type MyResult<T> = Result<T,Box<dyn Error>>;
 
+const PRG: &str = "headr";
+
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
 /// Rust version of head
@@ -15,66 +24,728 @@ pub struct Config {
         value_name = "FILE"
         )]
     files: Vec<String>,
-    /// Number of lines [default: 10]
+    /// Number of lines, with an optional K, M, G, ... suffix; with a leading
+    /// '-', all but the last LINES [default: 10, or all with --until/--while]
     #[arg(
         short('n'),
         long,
-        default_value_t = 10,
         conflicts_with("bytes"),
-        value_parser = clap::value_parser!(u64).range(1..),
+        allow_hyphen_values = true,
+        value_parser = parse_count,
         value_name = "LINES",
     )]
-    lines: u64,
-    /// Number of bytes
+    lines: Option<Count>,
+    /// Number of bytes, with an optional K, M, G, ... suffix; with a leading
+    /// '-', all but the last BYTES
     #[arg(
         short('c'),
         long,
         conflicts_with("lines"),
-        value_parser = clap::value_parser!(u64).range(1..),
+        allow_hyphen_values = true,
+        value_parser = parse_count,
         value_name = "BYTES"
     )]
-    bytes: Option<u64>,
+    bytes: Option<Count>,
+    /// Number of characters (Unicode scalar values)
+    #[arg(
+        long,
+        conflicts_with_all(["lines", "bytes", "graphemes"]),
+        value_parser = parse_size,
+        value_name = "CHARS"
+    )]
+    chars: Option<u64>,
+    /// Number of user-perceived characters (extended grapheme clusters)
+    #[arg(
+        long,
+        conflicts_with_all(["lines", "bytes"]),
+        value_parser = parse_size,
+        value_name = "GRAPHEMES"
+    )]
+    graphemes: Option<u64>,
+    /// Stop at the first line matching REGEX
+    #[arg(long, group("stop"), value_parser = Regex::new, value_name = "REGEX")]
+    until: Option<Regex>,
+    /// Stop at the first line not matching REGEX
+    #[arg(
+        long("while"),
+        group("stop"),
+        value_parser = Regex::new,
+        value_name = "REGEX"
+    )]
+    keep_while: Option<Regex>,
+    /// Also print the line that --until or --while stops at
+    #[arg(long, requires("stop"))]
+    inclusive: bool,
+    /// Count CSV records rather than lines, always keeping the header row
+    #[arg(
+        long,
+        conflicts_with_all(["bytes", "chars", "graphemes", "stop", "zero_terminated"])
+    )]
+    csv: bool,
+    /// Like --csv, for tab-separated values, which have no quoting: every
+    /// line is a record
+    #[arg(
+        long,
+        conflicts_with_all(["csv", "bytes", "chars", "graphemes", "stop", "zero_terminated"])
+    )]
+    tsv: bool,
+    /// End each file with a footer telling how many lines and bytes were left
+    /// out, as a record of its own; reads every input to the end, several
+    /// files at once
+    #[arg(long)]
+    summary: bool,
+    /// Never print headers giving file names
+    #[arg(short('q'), long, alias("silent"), overrides_with("verbose"))]
+    quiet: bool,
+    /// Always print headers giving file names
+    #[arg(short('v'), long, overrides_with("quiet"))]
+    verbose: bool,
+    /// Line delimiter is NUL, not newline
+    #[arg(short('z'), long)]
+    zero_terminated: bool,
+}
+
+/// What --chars and --graphemes count.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TextUnit {
+    Char,
+    Grapheme,
+}
+
+/// How much of each input to print.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Count {
+    /// The first N lines or bytes
+    First(u64),
+    /// Everything except the last N lines or bytes
+    AllButLast(u64),
+}
+
+// Multipliers for counts, as in GNU head.
+const SUFFIXES: [(&str, u64); 10] = [
+    ("b", 512),
+    ("kB", 1000),
+    ("K", 1 << 10),
+    ("KiB", 1 << 10),
+    ("MB", 1000 * 1000),
+    ("M", 1 << 20),
+    ("MiB", 1 << 20),
+    ("GB", 1000 * 1000 * 1000),
+    ("G", 1 << 30),
+    ("GiB", 1 << 30),
+];
+
+// cargo test tests::test_parse_count
+fn parse_count(val: &str) -> Result<Count, String> {
+    match val.strip_prefix('-') {
+        Some(magnitude) => parse_magnitude(magnitude, val).map(Count::AllButLast),
+        None => parse_size(val).map(Count::First),
+    }
+}
+
+// A positive count with an optional multiplier suffix.
+fn parse_size(val: &str) -> Result<u64, String> {
+    parse_magnitude(val, val)
+}
+
+// Parses `magnitude`, the unsigned part of `val`; errors quote all of `val`.
+fn parse_magnitude(magnitude: &str, val: &str) -> Result<u64, String> {
+    let digits = magnitude.len() - magnitude.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let (number, suffix) = magnitude.split_at(digits);
+    let multiplier = match suffix {
+        "" => Some(1),
+        _ => SUFFIXES.iter().find(|(s, _)| *s == suffix).map(|&(_, m)| m),
+    };
+    let (Some(multiplier), false) = (multiplier, number.is_empty()) else {
+        return Err(format!(
+            "invalid digit found in string; expected a number optionally followed by one of {}",
+            SUFFIXES.map(|(s, _)| s).join(", ")
+        ));
+    };
+    let too_large = || format!("{} is too large; the limit is {}", val, u64::MAX);
+    let n = number
+        .parse::<u64>()
+        .map_err(|_| too_large())?
+        .checked_mul(multiplier)
+        .ok_or_else(too_large)?;
+    match n {
+        0 => Err(format!("{} is not in 1..{}", val, u64::MAX)),
+        n => Ok(n),
+    }
 }
 
 pub fn get_args() -> MyResult<Config> {
     Ok(Config::parse())
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let multiple_files = config.files.len() > 1;
-    for (file, is_last_file) in config.files.iter().enumerate().map(|(i, w)| (w, i == config.files.len() - 1)) {
-        match open(&file) {
-            Err(err) => eprintln!("{}: {}", file, err),
-            Ok(mut read) => {
-                if multiple_files {
-                    println!("==> {} <==", file);
-                }
+/// Heads every input in turn. Inputs that cannot be read are reported on
+/// stderr and skipped; returns false if there were any.
+pub fn run(config: Config) -> MyResult<bool> {
+    run_to_writer(&config, io::stdout().lock())
+}
 
-                if let Some(byte) = config.bytes {
-                    let mut buffer = Vec::new();
-                    read.read_to_end(&mut buffer)?;
-                    let buffer = &buffer[..(byte as usize).min(buffer.len())];
-                    let text = String::from_utf8_lossy(buffer);
-                    print!("{}", text);
-                } else {
-                    for _ in 0..config.lines {
-                        let mut line = String::new();
-                        read.read_line(&mut line)?;
-                        print!("{}", line);
-                    }
+/// Like `run`, but writes to `out` instead of stdout.
+pub fn run_to_writer(config: &Config, mut out: impl Write) -> MyResult<bool> {
+    let show_headers = config.verbose || (config.files.len() > 1 && !config.quiet);
+    let mut first_header = true;
+    let mut header = |out: &mut dyn Write, file: &str| -> io::Result<()> {
+        if show_headers {
+            if !first_header {
+                writeln!(out)?;
+            }
+            writeln!(out, "==> {} <==", file)?;
+            first_header = false;
+        }
+        Ok(())
+    };
+    let mut all_read = true;
+    if config.summary {
+        summary::preview_files(config, |file, preview| {
+            if preview.opened {
+                header(&mut out, file)?;
+                out.write_all(&preview.output)?;
+            }
+            if let Some(message) = preview.error {
+                eprintln!("{}: {}", PRG, message);
+                all_read = false;
+            }
+            Ok(())
+        })?;
+        return Ok(all_read);
+    }
+    for file in &config.files {
+        let result = open(file).and_then(|read| {
+            header(&mut out, file)?;
+            head(read, config, &mut out)
+        });
+        // Anything but an InputError means stdout is gone, so give up.
+        if let Err(err) = result {
+            match err.downcast_ref::<InputError>() {
+                Some(input_err) => eprintln!("{}: {}", PRG, input_err.describe(file)),
+                None => return Err(err),
+            }
+            all_read = false;
+        }
+    }
+    Ok(all_read)
+}
+
+// Prints the part of `read` that `config` selects.
+fn head<'a>(read: Box<dyn BufRead + 'a>, config: &Config, out: &mut impl Write) -> MyResult<()> {
+    let records = config.lines.unwrap_or(Count::First(10));
+    match (config.csv, config.tsv) {
+        (true, _) => return table::head_table(read, records, b',', out),
+        (_, true) => return table::head_table(read, records, b'\t', out),
+        _ => {}
+    }
+    let delimiter = if config.zero_terminated { b'\0' } else { b'\n' };
+    let stop = match (&config.until, &config.keep_while) {
+        (Some(re), _) => Some(Stop::Until(re.clone())),
+        (_, Some(re)) => Some(Stop::While(re.clone())),
+        _ => None,
+    };
+    // The remaining limits then apply to what is left before the stop.
+    let (read, default_lines): (Box<dyn BufRead + 'a>, _) = match stop {
+        Some(stop) => (
+            Box::new(StopAt::new(read, stop, config.inclusive, delimiter)),
+            Count::First(u64::MAX),
+        ),
+        None => (read, Count::First(10)),
+    };
+    match (config.bytes, config.chars, config.graphemes) {
+        (Some(Count::First(bytes)), _, _) => first_bytes(read, bytes, out),
+        (Some(Count::AllButLast(bytes)), _, _) => all_but_last_bytes(read, bytes, out),
+        (_, Some(chars), _) => first_text_units(read, chars, TextUnit::Char, out),
+        (_, _, Some(graphemes)) => first_text_units(read, graphemes, TextUnit::Grapheme, out),
+        _ => match config.lines.unwrap_or(default_lines) {
+            Count::First(lines) => first_lines(read, lines, delimiter, out),
+            Count::AllButLast(lines) => all_but_last_lines(read, lines, delimiter, out),
+        },
+    }
+}
+
+/// Where --until or --while end the input.
+#[derive(Debug, Clone)]
+enum Stop {
+    Until(Regex),
+    While(Regex),
+}
+
+impl Stop {
+    fn stops_at(&self, line: &[u8]) -> bool {
+        match self {
+            Stop::Until(re) => re.is_match(line),
+            Stop::While(re) => !re.is_match(line),
+        }
+    }
+}
+
+/// A reader that ends where `stop` says, a line at a time.
+struct StopAt<R> {
+    read: R,
+    stop: Stop,
+    inclusive: bool,
+    delimiter: u8,
+    line: Vec<u8>,
+    pos: usize,
+    stopped: bool,
+}
+
+impl<R: BufRead> StopAt<R> {
+    fn new(read: R, stop: Stop, inclusive: bool, delimiter: u8) -> StopAt<R> {
+        StopAt {
+            read,
+            stop,
+            inclusive,
+            delimiter,
+            line: Vec::new(),
+            pos: 0,
+            stopped: false,
+        }
+    }
+
+    // The line without its delimiter, or a CR before a newline, so that
+    // patterns such as ^$ work on any line ending.
+    fn body(&self) -> &[u8] {
+        let line = self.line.strip_suffix(&[self.delimiter]).unwrap_or(&self.line);
+        match self.delimiter {
+            b'\n' => line.strip_suffix(b"\r").unwrap_or(line),
+            _ => line,
+        }
+    }
+}
+
+impl<R: BufRead> Read for StopAt<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for StopAt<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.line.len() && !self.stopped {
+            self.line.clear();
+            self.pos = 0;
+            self.read.read_until(self.delimiter, &mut self.line)?;
+            if !self.line.is_empty() && self.stop.stops_at(self.body()) {
+                self.stopped = true;
+                if !self.inclusive {
+                    self.line.clear();
                 }
             }
         }
-        if !is_last_file {
-            println!("");
+        Ok(&self.line[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.line.len());
+    }
+}
+
+// Copies no more than the first `n` bytes, so that even an endless input
+// is only read as far as needed.
+fn first_bytes(read: impl BufRead, n: u64, out: &mut impl Write) -> MyResult<()> {
+    let mut read = read.take(n);
+    loop {
+        let buf = read.fill_buf().map_err(InputError::Read)?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+        out.write_all(buf)?;
+        let len = buf.len();
+        read.consume(len);
+    }
+}
+
+/// Returns the first `n` lines of `read`. Each line keeps its ending, LF
+/// or CRLF, and the last one may have none.
+pub fn head_lines<R: BufRead>(read: R, n: u64) -> impl Iterator<Item = io::Result<Vec<u8>>> {
+    Records::new(read, b'\n').take(usize::try_from(n).unwrap_or(usize::MAX))
+}
+
+/// Returns the first `n` bytes of `read`, reading no further.
+pub fn head_bytes<R: Read>(read: R, n: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    read.take(n).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+// Splits input into records ending in `delimiter`; stops after an error.
+struct Records<R> {
+    read: R,
+    delimiter: u8,
+    done: bool,
+}
+
+impl<R: BufRead> Records<R> {
+    fn new(read: R, delimiter: u8) -> Records<R> {
+        Records {
+            read,
+            delimiter,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut record = Vec::new();
+        match self.read.read_until(self.delimiter, &mut record) {
+            Ok(0) => {
+                self.done = true;
+                None
+            }
+            Ok(_) => Some(Ok(record)),
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+fn first_lines(read: impl BufRead, n: u64, delimiter: u8, out: &mut impl Write) -> MyResult<()> {
+    let records = Records::new(read, delimiter);
+    for line in records.take(usize::try_from(n).unwrap_or(usize::MAX)) {
+        out.write_all(&line.map_err(InputError::Read)?)?;
+    }
+    Ok(())
+}
+
+// Copies the first `n` characters or grapheme clusters without ever
+// splitting one. Input is decoded a buffer at a time, so it does not
+// matter how long its lines are. The last unit seen may go on in the next
+// buffer, and so may the one before it if the last is an unfinished UTF-8
+// sequence, so those two are held back until more input arrives.
+fn first_text_units(
+    mut read: impl BufRead,
+    n: u64,
+    unit: TextUnit,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let mut remaining = usize::try_from(n).unwrap_or(usize::MAX);
+    let mut pending = Vec::new();
+    while remaining > 0 {
+        let buf = read.fill_buf().map_err(InputError::Read)?;
+        let at_end = buf.is_empty();
+        pending.extend_from_slice(buf);
+        let len = buf.len();
+        read.consume(len);
+
+        let units: Vec<_> = unit_lengths(&pending, unit)
+            .take(remaining.saturating_add(2))
+            .collect();
+        let held_back = if at_end { 0 } else { 2 };
+        let ready = units.len().saturating_sub(held_back).min(remaining);
+        let len = units[..ready].iter().sum();
+        out.write_all(&pending[..len])?;
+        pending.drain(..len);
+        remaining -= ready;
+        if at_end {
+            break;
         }
     }
     Ok(())
 }
 
+// The length in bytes of each character or grapheme cluster in `text`.
+// Invalid UTF-8 is passed through, each bad sequence counting as one unit
+// just as it would become one U+FFFD.
+fn unit_lengths(text: &[u8], unit: TextUnit) -> impl Iterator<Item = usize> + '_ {
+    text.utf8_chunks().flat_map(move |chunk| {
+        let valid: Box<dyn Iterator<Item = usize>> = match unit {
+            TextUnit::Char => Box::new(chunk.valid().chars().map(char::len_utf8)),
+            TextUnit::Grapheme => Box::new(chunk.valid().graphemes(true).map(str::len)),
+        };
+        let invalid = chunk.invalid().len();
+        valid.chain((invalid > 0).then_some(invalid))
+    })
+}
+
+// Streams `read` to `out` holding back the last `n` lines, as ended by
+// `delimiter`, in a ring buffer so that only those need to be in memory.
+fn all_but_last_lines(
+    mut read: impl BufRead,
+    n: u64,
+    delimiter: u8,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let n = usize::try_from(n).unwrap_or(usize::MAX);
+    let mut ring: VecDeque<Vec<u8>> = VecDeque::new();
+    let mut line = Vec::new();
+    loop {
+        if read.read_until(delimiter, &mut line).map_err(InputError::Read)? == 0 {
+            return Ok(());
+        }
+        ring.push_back(line);
+        // Reuse the buffer of the line that leaves the ring.
+        line = match ring.len() > n {
+            true => ring.pop_front().unwrap_or_default(),
+            false => Vec::new(),
+        };
+        out.write_all(&line)?;
+        line.clear();
+    }
+}
+
+// Like `all_but_last_lines`, holding back the last `n` bytes.
+fn all_but_last_bytes(mut read: impl BufRead, n: u64, out: &mut impl Write) -> MyResult<()> {
+    let n = usize::try_from(n).unwrap_or(usize::MAX);
+    let mut ring: VecDeque<u8> = VecDeque::new();
+    loop {
+        let buf = read.fill_buf().map_err(InputError::Read)?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+        ring.extend(buf);
+        let len = buf.len();
+        read.consume(len);
+
+        let excess = ring.len().saturating_sub(n);
+        if excess > 0 {
+            let (front, back) = ring.as_slices();
+            let from_front = excess.min(front.len());
+            out.write_all(&front[..from_front])?;
+            out.write_all(&back[..excess - from_front])?;
+            ring.drain(..excess);
+        }
+    }
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+        _ => {
+            let file = File::open(filename).map_err(InputError::Open)?;
+            Ok(Box::new(BufReader::new(file)))
+        }
+    }
+}
+
+/// A failure to read one input, as opposed to a failure to write the output.
+#[derive(Debug)]
+enum InputError {
+    Open(io::Error),
+    Read(io::Error),
+}
+
+impl InputError {
+    // Words the error like coreutils head does.
+    fn describe(&self, filename: &str) -> String {
+        let name = match filename {
+            "-" => "standard input".to_string(),
+            _ => filename.to_string(),
+        };
+        match self {
+            InputError::Open(e) => {
+                format!("cannot open '{}' for reading: {}", name, strip_os_error(e))
+            }
+            InputError::Read(e) => format!("error reading '{}': {}", name, strip_os_error(e)),
+        }
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::Open(e) | InputError::Read(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for InputError {}
+
+// "No such file or directory (os error 2)" -> "No such file or directory"
+fn strip_os_error(err: &io::Error) -> String {
+    let msg = err.to_string();
+    match msg.rfind(" (os error ") {
+        Some(i) if err.raw_os_error().is_some() => msg[..i].to_string(),
+        _ => msg,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        all_but_last_bytes, all_but_last_lines, first_text_units, head_bytes, head_lines,
+        parse_count, run_to_writer, Config, Count, Stop, StopAt, TextUnit,
+    };
+    use clap::Parser;
+    use regex::bytes::Regex;
+    use std::io::Read;
+    use pretty_assertions::assert_eq;
+    use std::io::{self, BufReader, Cursor};
+
+    #[test]
+    fn test_parse_count() {
+        assert_eq!(parse_count("3"), Ok(Count::First(3)));
+        assert_eq!(parse_count("-3"), Ok(Count::AllButLast(3)));
+        assert!(parse_count("0").is_err());
+        assert!(parse_count("-0").is_err());
+        assert!(parse_count("+3").is_err());
+        assert!(parse_count("--3").is_err());
+        assert_eq!(parse_count("4K"), Ok(Count::First(4096)));
+        assert_eq!(parse_count("-2kB"), Ok(Count::AllButLast(2000)));
+        assert_eq!(parse_count("3b"), Ok(Count::First(1536)));
+        assert_eq!(parse_count("1MiB"), Ok(Count::First(1 << 20)));
+        assert_eq!(parse_count("1M"), Ok(Count::First(1 << 20)));
+        assert_eq!(parse_count("5MB"), Ok(Count::First(5_000_000)));
+        assert_eq!(parse_count("2G"), Ok(Count::First(2 << 30)));
+        assert_eq!(parse_count("2GB"), Ok(Count::First(2_000_000_000)));
+        assert_eq!(parse_count("1GiB"), Ok(Count::First(1 << 30)));
+        assert!(parse_count("0K").is_err());
+        assert!(parse_count("K").is_err());
+        assert!(parse_count("4k").is_err());
+        assert!(parse_count("4 K").is_err());
+        assert!(parse_count("4KK").is_err());
+        assert!(parse_count("foo")
+            .unwrap_err()
+            .starts_with("invalid digit found in string; expected a number"));
+        assert_eq!(
+            parse_count("18446744073709551616").unwrap_err(),
+            "18446744073709551616 is too large; the limit is 18446744073709551615"
+        );
+        assert_eq!(
+            parse_count("-17179869184G").unwrap_err(),
+            "-17179869184G is too large; the limit is 18446744073709551615"
+        );
+        assert_eq!(
+            parse_count("-0").unwrap_err(),
+            "-0 is not in 1..18446744073709551615"
+        );
+    }
+
+    #[test]
+    fn test_all_but_last_lines() {
+        let head = |text: &str, n| {
+            let mut out = Vec::new();
+            all_but_last_lines(Cursor::new(text), n, b'\n', &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(head("a\nb\nc\nd\n", 1), "a\nb\nc\n");
+        assert_eq!(head("a\nb\nc\nd", 2), "a\nb\n");
+        assert_eq!(head("a\nb\n", 2), "");
+        assert_eq!(head("a\nb\n", 5), "");
+        assert_eq!(head("", 1), "");
+
+        let mut out = Vec::new();
+        all_but_last_lines(Cursor::new("a\0b\nc\0d\0"), 1, b'\0', &mut out).unwrap();
+        assert_eq!(out, b"a\0b\nc\0");
+    }
+
+    #[test]
+    fn test_all_but_last_bytes() {
+        let head = |text: &str, n, capacity| {
+            let mut out = Vec::new();
+            let read = std::io::BufReader::with_capacity(capacity, Cursor::new(text));
+            all_but_last_bytes(read, n, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(head("abcdef", 2, 8), "abcd");
+        assert_eq!(head("abcdef", 2, 1), "abcd");
+        assert_eq!(head("abcdef", 4, 3), "ab");
+        assert_eq!(head("abcdef", 6, 3), "");
+        assert_eq!(head("abc", 10, 2), "");
+    }
+
+    #[test]
+    fn test_first_text_units() {
+        // Every buffer size, so that units are split between buffers.
+        let head = |text: &[u8], n, unit| {
+            let heads: Vec<_> = (1..=text.len().max(1))
+                .map(|capacity| {
+                    let mut out = Vec::new();
+                    let read = BufReader::with_capacity(capacity, text);
+                    first_text_units(read, n, unit, &mut out).unwrap();
+                    out
+                })
+                .collect();
+            assert!(heads.iter().all(|out| *out == heads[0]), "{:?}", heads);
+            heads[0].clone()
+        };
+        let text = "Öne\ne\u{301}x\r\n🇳🇴!".as_bytes();
+        assert_eq!(head(text, 1, TextUnit::Char), "Ö".as_bytes());
+        assert_eq!(head(text, 5, TextUnit::Char), "Öne\ne".as_bytes());
+        assert_eq!(head(text, 6, TextUnit::Char), "Öne\ne\u{301}".as_bytes());
+        assert_eq!(head(text, 5, TextUnit::Grapheme), "Öne\ne\u{301}".as_bytes());
+        assert_eq!(head(text, 7, TextUnit::Grapheme), "Öne\ne\u{301}x\r\n".as_bytes());
+        assert_eq!(head(text, 8, TextUnit::Grapheme), "Öne\ne\u{301}x\r\n🇳🇴".as_bytes());
+        assert_eq!(head(text, 99, TextUnit::Grapheme), text);
+        assert_eq!(head(b"a\xff\xc3b", 2, TextUnit::Char), b"a\xff");
+        assert_eq!(head(b"a\xff\xc3b", 3, TextUnit::Grapheme), b"a\xff\xc3");
+        assert_eq!(head(b"", 3, TextUnit::Char), b"");
+    }
+
+    #[test]
+    fn test_first_text_units_endless() {
+        let mut out = Vec::new();
+        first_text_units(BufReader::new(io::repeat(0)), 5, TextUnit::Char, &mut out).unwrap();
+        assert_eq!(out, [0; 5]);
+        let mut out = Vec::new();
+        let read = BufReader::new(io::repeat(b'x'));
+        first_text_units(read, 3, TextUnit::Grapheme, &mut out).unwrap();
+        assert_eq!(out, b"xxx");
+    }
+
+    #[test]
+    fn test_stop_at() {
+        let head = |text: &str, stop: Stop, inclusive, delimiter| {
+            let mut read = StopAt::new(Cursor::new(text), stop, inclusive, delimiter);
+            let mut out = String::new();
+            read.read_to_string(&mut out).unwrap();
+            out
+        };
+        let until = |re| Stop::Until(Regex::new(re).unwrap());
+        let keep_while = |re| Stop::While(Regex::new(re).unwrap());
+        let text = "a: 1\r\nb: 2\r\n\r\nbody\r\n";
+        assert_eq!(head(text, until("^$"), false, b'\n'), "a: 1\r\nb: 2\r\n");
+        assert_eq!(head(text, until("^$"), true, b'\n'), "a: 1\r\nb: 2\r\n\r\n");
+        assert_eq!(head(text, until("^b"), false, b'\n'), "a: 1\r\n");
+        assert_eq!(head(text, until("nope"), false, b'\n'), text);
+        assert_eq!(head(text, keep_while(": "), false, b'\n'), "a: 1\r\nb: 2\r\n");
+        assert_eq!(head(text, keep_while("^a"), true, b'\n'), "a: 1\r\nb: 2\r\n");
+        assert_eq!(head("x\0y\0z", until("^y$"), false, b'\0'), "x\0");
+        assert_eq!(head("", until("^$"), false, b'\n'), "");
+    }
+
+    #[test]
+    fn test_head_lines() {
+        let head = |text: &str, n| -> Vec<Vec<u8>> {
+            head_lines(Cursor::new(text), n).collect::<Result<_, _>>().unwrap()
+        };
+        assert_eq!(head("a\r\nb\r\nc\r\n", 2), [b"a\r\n".to_vec(), b"b\r\n".to_vec()]);
+        assert_eq!(head("a\nb", 5), [b"a\n".to_vec(), b"b".to_vec()]);
+        assert_eq!(head("a\r\nb\r", 5), [b"a\r\n".to_vec(), b"b\r".to_vec()]);
+        assert_eq!(head("\n\n", 1), [b"\n".to_vec()]);
+        assert!(head("", 3).is_empty());
+    }
+
+    #[test]
+    fn test_head_bytes() {
+        assert_eq!(head_bytes(Cursor::new("a\r\nb"), 2).unwrap(), b"a\r");
+        assert_eq!(head_bytes(Cursor::new("a\r\nb"), 10).unwrap(), b"a\r\nb");
+        assert_eq!(head_bytes(&[0xff, 0xfe, 0][..], 2).unwrap(), [0xff, 0xfe]);
+    }
+
+    #[test]
+    fn test_run_to_writer() {
+        let config = Config::parse_from(["headr", "-n", "2", "tests/inputs/three.txt"]);
+        let mut out = Vec::new();
+        assert!(run_to_writer(&config, &mut out).unwrap());
+        assert_eq!(out, b"Three\r\nlines,\r\n");
+
+        let config = Config::parse_from(["headr", "-c", "3", "tests/inputs/one.txt", "nope"]);
+        let mut out = Vec::new();
+        assert!(!run_to_writer(&config, &mut out).unwrap());
+        assert_eq!(out, "==> tests/inputs/one.txt <==\nÖn".as_bytes());
     }
 }
@@ -0,0 +1,267 @@
+use crate::{head, open, Config, InputError, MyResult};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+use std::num::NonZero;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// What heading one file produced, for printing once the files before it
+/// are done.
+#[derive(Debug, Default)]
+pub(crate) struct Preview {
+    /// Whether the file could be opened, so it gets a header.
+    pub opened: bool,
+    pub output: Vec<u8>,
+    /// Why the file could not be opened or read to the end.
+    pub error: Option<String>,
+}
+
+/// Previews every file of `config` with a summary and hands the previews
+/// to `emit` in order. As each file has to be read to the end for its
+/// footer, several files are read at once; `emit` gets each preview as
+/// soon as it and the ones before it are ready. Standard input is only
+/// ever read on this thread, when its turn comes.
+pub(crate) fn preview_files(
+    config: &Config,
+    mut emit: impl FnMut(&str, Preview) -> MyResult<()>,
+) -> MyResult<()> {
+    let files = &config.files;
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZero::get)
+        .min(files.len());
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..workers {
+            let (tx, next) = (tx.clone(), &next);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(file) = files.get(i) else { break };
+                // Once `rx` is gone nobody wants the rest.
+                if file != "-" && tx.send((i, preview(file, config))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+        let mut ready = HashMap::new();
+        for (i, file) in files.iter().enumerate() {
+            let preview = match file.as_str() {
+                "-" => preview(file, config),
+                _ => loop {
+                    if let Some(preview) = ready.remove(&i) {
+                        break preview;
+                    }
+                    let (j, preview) = rx.recv().expect("a worker gave up early");
+                    ready.insert(j, preview);
+                },
+            };
+            emit(file, preview)?;
+        }
+        Ok(())
+    })
+}
+
+// Heads `file` with a summary into memory.
+fn preview(file: &str, config: &Config) -> Preview {
+    let mut preview = Preview::default();
+    let result = open(file).and_then(|read| {
+        preview.opened = true;
+        head_with_summary(read, config, &mut preview.output)
+    });
+    // Writing to memory cannot fail, so any error is about the input.
+    if let Err(err) = result {
+        preview.error = Some(match err.downcast_ref::<InputError>() {
+            Some(input_err) => input_err.describe(file),
+            None => err.to_string(),
+        });
+    }
+    preview
+}
+
+/// Bytes and lines seen so far. A final line without a delimiter still
+/// counts as a line.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Tally {
+    bytes: u64,
+    delimiters: u64,
+    ends_open: bool,
+}
+
+impl Tally {
+    fn add(&mut self, buf: &[u8], delimiter: u8) {
+        if let Some(&last) = buf.last() {
+            self.bytes += buf.len() as u64;
+            self.delimiters += buf.iter().filter(|&&b| b == delimiter).count() as u64;
+            self.ends_open = last != delimiter;
+        }
+    }
+
+    fn lines(&self) -> u64 {
+        self.delimiters + self.ends_open as u64
+    }
+}
+
+// Tallies everything that is consumed from `read`.
+struct TallyReader<R> {
+    read: R,
+    delimiter: u8,
+    tally: Tally,
+}
+
+impl<R: BufRead> Read for TallyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for TallyReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.read.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // The buffer is already filled, so this cannot need to read.
+        if let Ok(buf) = self.read.fill_buf() {
+            self.tally.add(&buf[..amt.min(buf.len())], self.delimiter);
+        }
+        self.read.consume(amt);
+    }
+}
+
+// Tallies everything that is written to `out`.
+struct TallyWriter<W> {
+    out: W,
+    delimiter: u8,
+    tally: Tally,
+}
+
+impl<W: Write> Write for TallyWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.out.write(buf)?;
+        self.tally.add(&buf[..written], self.delimiter);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Like `head`, followed by a footer telling how much of the input was
+/// left out. The rest of the input is read to count its lines and bytes.
+/// The footer is a record of its own, ended by the record delimiter.
+pub(crate) fn head_with_summary(
+    read: Box<dyn BufRead>,
+    config: &Config,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let delimiter = if config.zero_terminated { b'\0' } else { b'\n' };
+    let mut read = TallyReader {
+        read,
+        delimiter,
+        tally: Tally::default(),
+    };
+    let mut out = TallyWriter {
+        out,
+        delimiter,
+        tally: Tally::default(),
+    };
+    head(Box::new(&mut read), config, &mut out)?;
+    loop {
+        let len = read.fill_buf().map_err(InputError::Read)?.len();
+        if len == 0 {
+            break;
+        }
+        read.consume(len);
+    }
+
+    let (input, shown) = (read.tally, out.tally);
+    if shown.ends_open {
+        out.out.write_all(&[delimiter])?;
+    }
+    write!(
+        out.out,
+        "{}",
+        footer(
+            input.lines().saturating_sub(shown.lines()),
+            input.bytes.saturating_sub(shown.bytes)
+        )
+    )?;
+    out.out.write_all(&[delimiter])?;
+    Ok(())
+}
+
+// cargo test summary::tests::test_footer
+fn footer(lines: u64, bytes: u64) -> String {
+    let noun = if lines == 1 { "line" } else { "lines" };
+    format!("... ({} more {}, {})", thousands(lines), noun, human_size(bytes))
+}
+
+// 1234567 -> "1,234,567"
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+// Sizes in binary units, with one decimal below 10 of a unit.
+fn human_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in ["KB", "MB", "GB", "TB"] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    match unit {
+        "B" => format!("{} B", bytes),
+        _ if size < 10.0 => format!("{:.1} {}", size, unit),
+        _ => format!("{:.0} {}", size, unit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{footer, human_size, thousands, Tally};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_tally() {
+        let mut tally = Tally::default();
+        tally.add(b"a\nb", b'\n');
+        assert_eq!(tally.lines(), 2);
+        tally.add(b"c\n", b'\n');
+        assert_eq!(tally.lines(), 2);
+        tally.add(b"", b'\n');
+        assert_eq!((tally.bytes, tally.lines()), (5, 2));
+    }
+
+    #[test]
+    fn test_footer() {
+        assert_eq!(thousands(0), "0");
+        assert_eq!(thousands(999), "999");
+        assert_eq!(thousands(1234), "1,234");
+        assert_eq!(thousands(1234567), "1,234,567");
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1536), "1.5 KB");
+        assert_eq!(human_size(57_344), "56 KB");
+        assert_eq!(human_size(5 << 30), "5.0 GB");
+        assert_eq!(footer(1234, 57_344), "... (1,234 more lines, 56 KB)");
+        assert_eq!(footer(1, 4), "... (1 more line, 4 B)");
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{Count, InputError, MyResult};
+use csv::{ByteRecord, Reader, ReaderBuilder};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::mem;
+
+/// Prints the header row and then `count` records of CSV or TSV input.
+/// Records are copied exactly as they were read, so quoting is kept and a
+/// quoted CSV field spanning several lines is never cut. TSV has no
+/// quoting, so there every line is a record and quotes are plain text.
+pub(crate) fn head_table(
+    read: impl Read,
+    count: Count,
+    delimiter: u8,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let mut records = RawRecords::new(read, delimiter);
+    match records.next() {
+        Some(header) => out.write_all(&header.map_err(InputError::Read)?)?,
+        None => return Ok(()),
+    }
+    match count {
+        Count::First(n) => {
+            for record in records.take(usize::try_from(n).unwrap_or(usize::MAX)) {
+                out.write_all(&record.map_err(InputError::Read)?)?;
+            }
+        }
+        Count::AllButLast(n) => {
+            let n = usize::try_from(n).unwrap_or(usize::MAX);
+            let mut ring = VecDeque::new();
+            for record in records {
+                ring.push_back(record.map_err(InputError::Read)?);
+                if ring.len() > n {
+                    out.write_all(&ring.pop_front().unwrap_or_default())?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Keeps a copy of everything the CSV reader takes from `read`.
+struct Tee<R> {
+    read: R,
+    seen: Vec<u8>,
+}
+
+impl<R: Read> Read for Tee<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.read.read(buf)?;
+        self.seen.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+}
+
+/// The raw bytes of each record, including its line ending and anything
+/// the parser skipped before it, such as blank lines.
+struct RawRecords<R> {
+    reader: Reader<Tee<R>>,
+    record: ByteRecord,
+    // Input offset of the first byte in `seen`
+    start: u64,
+    // The parser ends a record at the CR of a CRLF, so each record is held
+    // back until the next one shows whether its LF has to be moved over.
+    pending: Option<Vec<u8>>,
+}
+
+impl<R: Read> RawRecords<R> {
+    fn new(read: R, delimiter: u8) -> RawRecords<R> {
+        let reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(delimiter)
+            .quoting(delimiter != b'\t')
+            .from_reader(Tee {
+                read,
+                seen: Vec::new(),
+            });
+        RawRecords {
+            reader,
+            record: ByteRecord::new(),
+            start: 0,
+            pending: None,
+        }
+    }
+
+    // Takes the bytes of the record that was just parsed.
+    fn take_raw(&mut self) -> Vec<u8> {
+        let end = self.reader.position().byte();
+        let len = (end - self.start) as usize;
+        self.start = end;
+        self.reader.get_mut().seen.drain(..len).collect()
+    }
+}
+
+impl<R: Read> Iterator for RawRecords<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.reader.read_byte_record(&mut self.record) {
+                Err(err) => return Some(Err(err.into())),
+                Ok(false) => {
+                    // Whatever is left, such as the LF of a final CRLF or
+                    // trailing blank lines, belongs to the last record.
+                    let rest = mem::take(&mut self.reader.get_mut().seen);
+                    return self.pending.take().map(|mut record| {
+                        record.extend(rest);
+                        Ok(record)
+                    });
+                }
+                Ok(true) => {
+                    let raw = self.take_raw();
+                    let Some(mut record) = self.pending.replace(raw) else {
+                        continue;
+                    };
+                    if let Some(next) = self.pending.as_mut() {
+                        if record.ends_with(b"\r") && next.first() == Some(&b'\n') {
+                            record.push(next.remove(0));
+                        }
+                    }
+                    return Some(Ok(record));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::head_table;
+    use crate::Count;
+    use pretty_assertions::assert_eq;
+
+    fn head(text: &str, count: Count, delimiter: u8) -> String {
+        let mut out = Vec::new();
+        head_table(text.as_bytes(), count, delimiter, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_head_table() {
+        let text = "id,note\r\n1,\"two\r\nlines\"\r\n2,plain\r\n3,\"a \"\"quote\"\"\"\r\n";
+        assert_eq!(head(text, Count::First(1), b','), "id,note\r\n1,\"two\r\nlines\"\r\n");
+        assert_eq!(head(text, Count::First(3), b','), text);
+        assert_eq!(head(text, Count::First(9), b','), text);
+        assert_eq!(
+            head(text, Count::AllButLast(2), b','),
+            "id,note\r\n1,\"two\r\nlines\"\r\n"
+        );
+        assert_eq!(head(text, Count::AllButLast(9), b','), "id,note\r\n");
+        assert_eq!(head("a,b\n1,\"x\ny\"", Count::First(1), b','), "a,b\n1,\"x\ny\"");
+        assert_eq!(head("a\tb\n1\t\"x\ny\"", Count::First(1), b'\t'), "a\tb\n1\t\"x\n");
+        assert_eq!(head("a,b\n\n1,2\n2,3\n", Count::First(1), b','), "a,b\n\n1,2\n");
+        assert_eq!(head("a,b\n1,2\n\n\n", Count::First(5), b','), "a,b\n1,2\n\n\n");
+        assert_eq!(head("", Count::First(1), b','), "");
+    }
+}
@@ -12,6 +12,10 @@ const ONE: &str = "./tests/inputs/one.txt";
 const TWO: &str = "./tests/inputs/two.txt";
 const THREE: &str = "./tests/inputs/three.txt";
 const TWELVE: &str = "./tests/inputs/twelve.txt";
+const NUL: &str = "./tests/inputs/nul.txt";
+const COMBINING: &str = "./tests/inputs/combining.txt";
+const FRONT_MATTER: &str = "./tests/inputs/front-matter.txt";
+const BOOKS_CSV: &str = "./tests/inputs/books.csv";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -86,11 +90,48 @@ fn dies_bytes_and_lines() -> Result<()> {
 #[test]
 fn skips_bad_file() -> Result<()> {
     let bad = gen_bad_file();
-    let expected = format!("{bad}: .* [(]os error 2[)]");
+    let expected = format!(
+        "headr: cannot open '{bad}' for reading: No such file or directory\n"
+    );
     Command::cargo_bin(PRG)?
         .args([EMPTY, &bad, ONE])
         .assert()
-        .stderr(predicate::str::is_match(expected)?);
+        .failure()
+        .code(1)
+        .stderr(expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_good_and_bad_inputs() -> Result<()> {
+    let bad = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/mixed.n1.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1", TWO, &bad, "tests/inputs", TWELVE])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(expected)
+        .stderr(format!(
+            "headr: cannot open '{bad}' for reading: No such file or directory\n\
+            headr: error reading 'tests/inputs': Is a directory\n"
+        ));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_directory_only() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1", "tests/inputs"])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout("")
+        .stderr("headr: error reading 'tests/inputs': Is a directory\n");
 
     Ok(())
 }
@@ -423,3 +464,407 @@ fn multiple_files_c4() -> Result<()> {
         "tests/expected/all.c4.out",
     )
 }
+
+// --------------------------------------------------
+#[test]
+fn three_n_minus2() -> Result<()> {
+    run(&[THREE, "-n", "-2"], "tests/expected/three.txt.n-2.out")
+}
+
+#[test]
+fn three_c_minus4() -> Result<()> {
+    run(&[THREE, "-c", "-4"], "tests/expected/three.txt.c-4.out")
+}
+
+#[test]
+fn twelve_n_minus2() -> Result<()> {
+    run(&[TWELVE, "-n", "-2"], "tests/expected/twelve.txt.n-2.out")
+}
+
+#[test]
+fn twelve_c_minus4() -> Result<()> {
+    run(&[TWELVE, "-c", "-4"], "tests/expected/twelve.txt.c-4.out")
+}
+
+#[test]
+fn twelve_n_minus2_stdin() -> Result<()> {
+    run_stdin(&["-n", "-2"], TWELVE, "tests/expected/twelve.txt.n-2.out")
+}
+
+#[test]
+fn twelve_c_minus4_stdin() -> Result<()> {
+    run_stdin(&["--bytes=-4"], TWELVE, "tests/expected/twelve.txt.c-4.out")
+}
+
+#[test]
+fn twelve_n_minus_all() -> Result<()> {
+    run(&[TWELVE, "-n", "-20"], "tests/expected/empty.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn one_c1_raw_bytes() -> Result<()> {
+    let output = Command::cargo_bin(PRG)?.args(["-c", "1", ONE]).output()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"\xc3");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn bytes_from_endless_input() -> Result<()> {
+    let output = Command::cargo_bin(PRG)?
+        .args(["-c", "5", "/dev/zero"])
+        .timeout(std::time::Duration::from_secs(10))
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"\0\0\0\0\0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn chars_and_graphemes_from_endless_input() -> Result<()> {
+    for unit in ["--chars", "--graphemes"] {
+        let output = Command::cargo_bin(PRG)?
+            .args([unit, "5", "/dev/zero"])
+            .timeout(std::time::Duration::from_secs(10))
+            .output()?;
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"\0\0\0\0\0");
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files_quiet() -> Result<()> {
+    run(
+        &["-q", "-n", "2", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.n2.q.out",
+    )
+}
+
+#[test]
+fn multiple_files_quiet_then_verbose() -> Result<()> {
+    run(
+        &["-n", "2", "-q", "-v", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.n2.qv.out",
+    )
+}
+
+#[test]
+fn one_verbose() -> Result<()> {
+    run(&["--verbose", ONE], "tests/expected/one.txt.v.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn nul_zero_terminated_n2() -> Result<()> {
+    run(&["-z", "-n", "2", NUL], "tests/expected/nul.txt.z.n2.out")
+}
+
+#[test]
+fn nul_zero_terminated_n_minus1_stdin() -> Result<()> {
+    run_stdin(
+        &["--zero-terminated", "-n", "-1"],
+        NUL,
+        "tests/expected/nul.txt.z.n-1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_with_suffix_stdin() -> Result<()> {
+    let input = "x".repeat(5000);
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .args(["-c", "4KiB"])
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout.len(), 4096);
+    Ok(())
+}
+
+#[test]
+fn lines_with_suffix_stdin() -> Result<()> {
+    let input: String = (1..=1500).map(|n| format!("{n}\n")).collect();
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .args(["-n", "-1kB"])
+        .output()?;
+    assert!(output.status.success());
+    let expected: String = (1..=500).map(|n| format!("{n}\n")).collect();
+    assert_eq!(String::from_utf8(output.stdout)?, expected);
+    Ok(())
+}
+
+#[test]
+fn dies_bad_suffix() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "4X", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "expected a number optionally followed by one of b, kB, K",
+        ));
+    Ok(())
+}
+
+#[test]
+fn dies_count_overflow() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "99999999999G", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "99999999999G is too large; the limit is 18446744073709551615",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn one_chars1() -> Result<()> {
+    let output = Command::cargo_bin(PRG)?.args(["--chars", "1", ONE]).output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "Ö");
+    Ok(())
+}
+
+#[test]
+fn combining_chars_stdin() -> Result<()> {
+    let input = fs::read(COMBINING)?;
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .args(["--chars", "4"])
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "Cafe");
+    Ok(())
+}
+
+#[test]
+fn combining_graphemes() -> Result<()> {
+    let output = Command::cargo_bin(PRG)?
+        .args(["--graphemes", "4", COMBINING])
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "Cafe\u{301}");
+    Ok(())
+}
+
+#[test]
+fn combining_graphemes_across_lines() -> Result<()> {
+    let output = Command::cargo_bin(PRG)?
+        .args(["--graphemes", "13", COMBINING])
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "Cafe\u{301} cre\u{300}me\nna"
+    );
+    Ok(())
+}
+
+#[test]
+fn dies_chars_and_graphemes() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--chars", "1", "--graphemes", "1", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn front_matter_until_blank() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--until", "^$", FRONT_MATTER])
+        .assert()
+        .success()
+        .stdout("---\ntitle: Report\nauthor: Ada\n---\n");
+    Ok(())
+}
+
+#[test]
+fn front_matter_until_inclusive_stdin() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .write_stdin(fs::read_to_string(FRONT_MATTER)?)
+        .args(["--until", "^---$", "--inclusive"])
+        .assert()
+        .success()
+        .stdout("---\n");
+    Ok(())
+}
+
+#[test]
+fn front_matter_while() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--while", r"^(---|\w+: )", "--inclusive", FRONT_MATTER])
+        .assert()
+        .success()
+        .stdout("---\ntitle: Report\nauthor: Ada\n---\n\n");
+    Ok(())
+}
+
+#[test]
+fn front_matter_until_with_max_lines() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "2", "--until", "^$", FRONT_MATTER])
+        .assert()
+        .success()
+        .stdout("---\ntitle: Report\n");
+    Ok(())
+}
+
+#[test]
+fn twelve_until_no_match_prints_all() -> Result<()> {
+    run(&["--until", "^nope$", TWELVE], "tests/inputs/twelve.txt")
+}
+
+#[test]
+fn dies_until_and_while() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--until", "a", "--while", "b", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the argument '--until <REGEX>' cannot be used with '--while <REGEX>'",
+        ));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_until_regex() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--until", "(", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value '(' for '--until <REGEX>'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files_summary() -> Result<()> {
+    run(
+        &["--summary", "-n", "2", EMPTY, ONE, TWELVE],
+        "tests/expected/all.n2.summary.out",
+    )
+}
+
+#[test]
+fn twelve_c5_summary() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--summary", "-c", "5", TWELVE])
+        .assert()
+        .success()
+        .stdout("one\nt\n... (10 more lines, 58 B)\n");
+    Ok(())
+}
+
+#[test]
+fn summary_stdin() -> Result<()> {
+    let input: String = (1..=5000).map(|n| format!("{n}\n")).collect();
+    Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .args(["--summary", "-n", "1"])
+        .assert()
+        .success()
+        .stdout("1\n... (4,999 more lines, 23 KB)\n");
+    Ok(())
+}
+
+#[test]
+fn summary_zero_terminated() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .write_stdin("a\0b\0c\0")
+        .args(["-z", "-n", "1", "--summary"])
+        .assert()
+        .success()
+        .stdout("a\0... (2 more lines, 4 B)\0");
+    Ok(())
+}
+
+#[test]
+fn summary_many_files_in_order() -> Result<()> {
+    let bad = gen_bad_file();
+    let mut args = vec!["--summary", "-n", "1"];
+    let mut expected = String::new();
+    let mut errors = String::new();
+    for i in 0..20 {
+        let (file, preview) = match i % 3 {
+            0 => (TWO, "Two lines.\n... (1 more line, 12 B)\n"),
+            1 => (TWELVE, "one\n... (11 more lines, 59 B)\n"),
+            _ => (bad.as_str(), ""),
+        };
+        args.push(file);
+        if preview.is_empty() {
+            errors += &format!(
+                "headr: cannot open '{bad}' for reading: No such file or directory\n"
+            );
+        } else {
+            let sep = if expected.is_empty() { "" } else { "\n" };
+            expected += &format!("{sep}==> {file} <==\n{preview}");
+        }
+    }
+    Command::cargo_bin(PRG)?
+        .args(&args)
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(errors);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn books_csv_n2() -> Result<()> {
+    run(&["--csv", "-n", "2", BOOKS_CSV], "tests/expected/books.csv.n2.out")
+}
+
+#[test]
+fn books_csv_n2_stdin() -> Result<()> {
+    run_stdin(&["--csv", "-n", "2"], BOOKS_CSV, "tests/expected/books.csv.n2.out")
+}
+
+#[test]
+fn books_csv_all_but_last2() -> Result<()> {
+    run(&["--csv", "-n", "-2", BOOKS_CSV], "tests/expected/books.csv.n2.out")
+}
+
+#[test]
+fn books_tsv_n1() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--tsv", "-n", "1", "tests/inputs/books.tsv"])
+        .assert()
+        .success()
+        .stdout("title\tauthor\n\"Moby-Dick\"\tMelville\n");
+    Ok(())
+}
+
+#[test]
+fn tsv_unbalanced_quote() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--tsv", "-n", "2"])
+        .write_stdin("title\tnote\nA\t\"unbalanced\nB\tplain\nC\tplain\n")
+        .assert()
+        .success()
+        .stdout("title\tnote\nA\t\"unbalanced\nB\tplain\n");
+    Ok(())
+}
+
+#[test]
+fn dies_csv_and_bytes() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--csv", "-c", "3", BOOKS_CSV])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the argument '--csv' cannot be used with '--bytes <BYTES>'",
+        ));
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::env;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+use wcr::Scan;
+
+// About 64 MiB of mostly ASCII text with some tabs and wide characters.
+fn sample_file() -> PathBuf {
+    let path = env::temp_dir().join("wcr-bench-sample.txt");
+    let line = "The quick brown fox\tjumps over the lazy dog. 日本語のテキスト, żółw\n";
+    let size = 64 << 20;
+    if fs::metadata(&path).map_or(true, |m| m.len() as usize != size / line.len() * line.len()) {
+        fs::write(&path, line.repeat(size / line.len())).unwrap();
+    }
+    path
+}
+
+fn count(c: &mut Criterion) {
+    let path = sample_file();
+    let filename = path.to_str().unwrap();
+    let mut group = c.benchmark_group("count");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(fs::metadata(&path).unwrap().len()));
+    // The default counts, and -l, which need not decode anything.
+    for (name, scan) in [("", Scan::Text), ("-l ", Scan::Lines)] {
+        group.bench_function(format!("{}stream", name), |b| {
+            b.iter(|| wcr::count(BufReader::new(File::open(&path).unwrap()), scan).unwrap())
+        });
+        for threads in [2, 4, 8] {
+            let id = BenchmarkId::new(format!("{}threads", name), threads);
+            group.bench_with_input(id, &threads, |b, &n| {
+                b.iter(|| wcr::count_file(filename, n, scan).unwrap().unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, count);
+criterion_main!(benches);
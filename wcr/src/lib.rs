@@ -1,7 +1,17 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use std::error::Error;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
+use std::ffi::OsString;
+use std::mem;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use unicode_width::UnicodeWidthChar;
+
+mod parallel;
+mod report;
+
+pub use parallel::count_file;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -14,7 +24,7 @@ pub struct Config {
         value_name = "FILE",
         default_value = "-",
         )]
-        files: Vec<String>,
+        files: Vec<PathBuf>,
         /// Show byte count
         #[arg(short('c'), long)]
         bytes: bool,
@@ -27,54 +37,117 @@ pub struct Config {
         /// Show word count
         #[arg(short('w'), long)]
         words: bool,
+        /// Show the display width of the longest line
+        #[arg(short('L'), long)]
+        max_line_length: bool,
+        /// Warn about invalid UTF-8, which --chars does not count
+        #[arg(long)]
+        report_invalid: bool,
+        /// Read NUL-separated input file names from F ("-" for stdin)
+        #[arg(long, value_name = "F", conflicts_with("files"))]
+        files0_from: Option<String>,
+        /// When to print a line with total counts
+        #[arg(long, value_name = "WHEN", value_enum, default_value_t = Total::Auto)]
+        total: Total,
+        /// Count large files in up to N parts at once
+        #[arg(long, value_name = "N", default_value_t = 1, value_parser = parse_threads)]
+        threads: usize,
+        /// How to print the counts
+        #[arg(long, value_name = "FORMAT", value_enum, default_value_t = Format::Table)]
+        format: Format,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    /// Aligned columns, as wide as the total size of the files needs
+    Table,
+    Json,
+    Csv,
+    Tsv,
+}
+
+/// How closely the input has to be looked at to get the wanted counts.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Scan {
+    /// Only bytes and newlines are counted, without decoding anything
+    Lines,
+    /// Characters are decoded to count words, characters, invalid
+    /// sequences and line widths as well
+    #[default]
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Total {
+    /// Only when there is more than one file
+    Auto,
+    /// Even for a single file
+    Always,
+    /// Print only the total, without a line per file
+    Only,
+    /// Not even for several files
+    Never,
 }
 
 impl Config {
     fn finalize(&mut self) {
-        if [self.lines, self.bytes, self.words, self.chars].iter().all(|v| !v) {
+        if self.counts() == 0 {
             self.lines = true;
             self.words = true;
             self.bytes = true;
         }
     }
+
+    // How many counts are shown for each file.
+    fn counts(&self) -> usize {
+        [
+            self.lines,
+            self.words,
+            self.bytes,
+            self.chars,
+            self.max_line_length,
+        ]
+        .iter()
+        .filter(|&&v| v)
+        .count()
+    }
+
+    fn scan(&self) -> Scan {
+        if self.words || self.chars || self.max_line_length || self.report_invalid {
+            Scan::Text
+        } else {
+            Scan::Lines
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub struct FileInfo {
     num_lines: usize,
     num_words: usize,
     num_bytes: usize,
     num_chars: usize,
+    num_invalid: usize,
+    max_line_length: usize,
 }
 
 impl FileInfo {
+    // Lines are not joined across files, so the longest line is a maximum.
     fn add(&mut self, other: &FileInfo) {
         self.num_lines += other.num_lines;
         self.num_words += other.num_words;
         self.num_bytes += other.num_bytes;
         self.num_chars += other.num_chars;
+        self.num_invalid += other.num_invalid;
+        self.max_line_length = self.max_line_length.max(other.max_line_length);
     }
 }
 
-impl FileInfo {
-    fn print(&self, filename: &str, config: &Config) {
-        if config.lines {
-            print!("{}", format!("{:>8}", self.num_lines));
-        }
-        if config.words {
-            print!("{}", format!("{:>8}", self.num_words));
-        }
-        if config.bytes {
-            print!("{}", format!("{:>8}", self.num_bytes));
-        }
-        if config.chars {
-            print!("{}", format!("{:>8}" , self.num_chars));
-        }
-        if filename != "-" {
-            println!(" {}", filename);
-        } else {
-            println!();
-        }
+fn parse_threads(val: &str) -> Result<usize, String> {
+    match val.parse() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(e.to_string()),
     }
 }
 
@@ -85,79 +158,377 @@ pub fn get_args() -> MyResult<Config> {
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    let mut total_file_info = FileInfo {
-        num_lines: 0,
-        num_words: 0,
-        num_bytes: 0,
-        num_chars: 0,
+    let files = match &config.files0_from {
+        Some(source) => read_files0(source)?,
+        None => config.files.clone(),
     };
-    for filename in &config.files {
-        match open(filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(read) => {
-                let info = count(read)?;
-                total_file_info.add(&info);
-                info.print(filename, &config);
+    let width = report::table_width(&files, &config);
+    let mut report = report::Report::new(io::stdout().lock(), &config, width);
+    let mut total_file_info = FileInfo::default();
+    for (i, filename) in files.iter().enumerate() {
+        if let Some(source) = &config.files0_from {
+            if let Some(err) = check_listed_name(source, i + 1, filename) {
+                eprintln!("{}", err);
+                continue;
             }
         }
+        let counted = open(filename).and_then(|read| {
+            match count_file(filename, config.threads, config.scan())? {
+                Some(info) => Ok(info),
+                None => count(read, config.scan()),
+            }
+        });
+        // Errors reading a file only skip it; errors writing the report end
+        // the run.
+        let info = match counted {
+            Ok(info) => info,
+            Err(err) => {
+                eprintln!("{}: {}", filename.display(), err);
+                continue;
+            }
+        };
+        if config.report_invalid && info.num_invalid > 0 {
+            eprintln!(
+                "{}: {} invalid UTF-8 sequences",
+                filename.display(),
+                info.num_invalid
+            );
+        }
+        total_file_info.add(&info);
+        if config.total != Total::Only {
+            report.file(&filename.display().to_string(), &info)?;
+        }
+    }
+    let total = match config.total {
+        Total::Auto => files.len() > 1,
+        Total::Always | Total::Only => true,
+        Total::Never => false,
+    };
+    report.finish(total.then_some(&total_file_info))
+}
+
+// Names are separated by NUL bytes, so any name can be listed, and there
+// is no limit on how many there are. Names are kept as raw bytes, as they
+// need not be UTF-8.
+fn read_files0(source: &str) -> MyResult<Vec<PathBuf>> {
+    let read = open(Path::new(source))
+        .map_err(|e| format!("cannot open '{}' for reading: {}", source, e))?;
+    let mut names = Vec::new();
+    for name in read.split(b'\0') {
+        names.push(PathBuf::from(OsString::from_vec(name?)));
     }
-    if config.files.len() > 1 {
-        total_file_info.print("total", &config);
+    Ok(names)
+}
+
+// Describes why a name read with --files0-from cannot be counted.
+fn check_listed_name(source: &str, number: usize, filename: &Path) -> Option<String> {
+    if filename.as_os_str().is_empty() {
+        Some(format!("{}:{}: invalid zero-length file name", source, number))
+    } else if source == "-" && filename == Path::new("-") {
+        Some("when reading file names from standard input, no file name of '-' allowed".to_string())
+    } else {
+        None
     }
-    Ok(())
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
+fn open(filename: &Path) -> MyResult<Box<dyn BufRead>> {
+    match filename.to_str() {
+        Some("-") => Ok(Box::new(BufReader::new(io::stdin()))),
         _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
     }
 }
 
-pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
-    let mut num_lines = 0;
-    let mut num_words = 0;
-    let mut num_bytes = 0;
-    let mut num_chars = 0;
+/// Counts `file`. With `Scan::Lines` only lines and bytes are counted.
+pub fn count(file: impl BufRead, scan: Scan) -> MyResult<FileInfo> {
+    let mut counter = Counter::new(scan);
+    counter.read_all(file)?;
+    Ok(counter.finish())
+}
+
+/// Counts raw bytes a chunk at a time, so any input can be counted. UTF-8
+/// sequences may be split across chunks; bytes that are not valid UTF-8
+/// are counted as invalid instead of as characters.
+///
+/// Parts of a file can also be counted separately and then merged, which
+/// is why the start of the first line and the first word boundary are
+/// kept: a part cannot know what came before it.
+#[derive(Debug, Default)]
+struct Counter {
+    scan: Scan,
+    info: FileInfo,
+    in_word: bool,
+    // Display width of the current line so far
+    line_width: usize,
+    // The start of a UTF-8 sequence that the next chunk may complete
+    partial: Vec<u8>,
+    // Whether a word started before any whitespace was seen
+    first_break: Option<bool>,
+    // Width of the first line when it ended
+    head_width: Option<usize>,
+    // Width of the first line before its first tab
+    head_tab: Option<usize>,
+}
+
+impl Counter {
+    fn new(scan: Scan) -> Counter {
+        Counter {
+            scan,
+            ..Counter::default()
+        }
+    }
+
+    fn read_all(&mut self, mut read: impl BufRead) -> io::Result<()> {
+        loop {
+            let buf = read.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(());
+            }
+            self.update(buf);
+            let len = buf.len();
+            read.consume(len);
+        }
+    }
+
+    fn update(&mut self, chunk: &[u8]) {
+        self.info.num_bytes += chunk.len();
+        self.info.num_lines += memchr::memchr_iter(b'\n', chunk).count();
+        if self.scan == Scan::Lines {
+            return;
+        }
+
+        let mut rest = chunk;
+        if !self.partial.is_empty() {
+            rest = self.complete_partial(rest);
+        }
+        for piece in rest.utf8_chunks() {
+            for c in piece.valid().chars() {
+                self.char(c);
+            }
+            let invalid = piece.invalid();
+            if invalid.is_empty() {
+                continue;
+            }
+            // Only the very end of a chunk can be an incomplete sequence.
+            let at_end = invalid.as_ptr_range().end == rest.as_ptr_range().end;
+            if at_end && std::str::from_utf8(invalid).is_err_and(|e| e.error_len().is_none()) {
+                self.partial.extend_from_slice(invalid);
+            } else {
+                self.invalid();
+            }
+        }
+    }
+
+    // Finishes the sequence begun in the previous chunk, returning what
+    // is left of `chunk`.
+    fn complete_partial<'a>(&mut self, chunk: &'a [u8]) -> &'a [u8] {
+        let carried = self.partial.len();
+        let mut seq = mem::take(&mut self.partial);
+        seq.extend(chunk.iter().take(4 - carried));
+        let (valid, error) = match std::str::from_utf8(&seq) {
+            Ok(valid) => (valid, None),
+            Err(e) => (
+                std::str::from_utf8(&seq[..e.valid_up_to()]).unwrap_or_default(),
+                Some(e),
+            ),
+        };
+        if let Some(c) = valid.chars().next() {
+            self.char(c);
+            return &chunk[c.len_utf8() - carried..];
+        }
+        match error.and_then(|e| e.error_len()) {
+            Some(len) => {
+                self.invalid();
+                &chunk[len.saturating_sub(carried)..]
+            }
+            None => {
+                // Still incomplete, so this chunk was tiny.
+                self.partial = seq;
+                &[]
+            }
+        }
+    }
+
+    // As in GNU wc, only printable characters start a word, while control
+    // characters and invalid sequences neither start nor end one.
+    fn char(&mut self, c: char) {
+        self.info.num_chars += 1;
+        self.advance_line(c);
+        if c.is_whitespace() {
+            self.in_word = false;
+            self.first_break.get_or_insert(false);
+        } else if !self.in_word && !c.is_control() {
+            self.in_word = true;
+            self.info.num_words += 1;
+            self.first_break.get_or_insert(true);
+        }
+    }
+
+    // Tabs move to the next multiple of 8 and a carriage return or form
+    // feed starts over like a newline. Wide characters take two columns,
+    // while combining marks and other control characters take none.
+    fn advance_line(&mut self, c: char) {
+        match c {
+            '\t' => {
+                if self.head_width.is_none() && self.head_tab.is_none() {
+                    self.head_tab = Some(self.line_width);
+                }
+                self.line_width = next_tab_stop(self.line_width);
+            }
+            '\n' | '\r' | '\x0c' => self.end_line(),
+            _ => self.line_width += c.width().unwrap_or(0),
+        }
+    }
 
-    let mut buf = String::new();
+    fn end_line(&mut self) {
+        self.head_width.get_or_insert(self.line_width);
+        self.info.max_line_length = self.info.max_line_length.max(self.line_width);
+        self.line_width = 0;
+    }
+
+    // The column a width on the first line would reach if the line had
+    // started in column `start`. Only the first tab depends on `start`;
+    // every later one is the same distance from a tab stop.
+    fn shift(&self, width: usize, start: usize) -> usize {
+        match self.head_tab {
+            Some(tab) => next_tab_stop(start + tab) + width - next_tab_stop(tab),
+            None => start + width,
+        }
+    }
+
+    /// Joins the counts of the part that follows this one. Both parts must
+    /// have flushed any partial UTF-8 sequence.
+    fn merge(mut self, next: Counter) -> Counter {
+        let col = self.line_width;
+        self.info.add(&next.info);
+        // A word that straddles the two parts was counted in both.
+        if self.in_word && next.first_break == Some(true) {
+            self.info.num_words -= 1;
+        }
+        if next.first_break.is_some() {
+            self.in_word = next.in_word;
+        }
+        self.first_break = self.first_break.or(next.first_break);
+
+        let straddling = next.head_width.map(|width| next.shift(width, col));
+        if self.head_width.is_none() {
+            self.head_tab = self.head_tab.or(next.head_tab.map(|tab| col + tab));
+            self.head_width = straddling;
+        }
+        self.info.max_line_length = self.info.max_line_length.max(straddling.unwrap_or(0));
+        self.line_width = match next.head_width {
+            Some(_) => next.line_width,
+            None => next.shift(next.line_width, col),
+        };
+        self
+    }
+
+    fn invalid(&mut self) {
+        self.info.num_invalid += 1;
+    }
+
+    // Whatever is left can no longer be completed.
+    fn flush_partial(&mut self) {
+        if !mem::take(&mut self.partial).is_empty() {
+            self.invalid();
+        }
+    }
 
-    while {
-        let read_bytes = file.read_line(&mut buf)?;
-        num_bytes += read_bytes;
-        read_bytes != 0
-    } {
-        num_words += buf.split_whitespace().count();
-        num_chars += buf.chars().count();
-        num_lines += 1;
-        buf.clear();
+    fn finish(mut self) -> FileInfo {
+        self.flush_partial();
+        self.end_line();
+        self.info
     }
+}
 
-    Ok(FileInfo {
-        num_lines,
-        num_words,
-        num_bytes,
-        num_chars,
-    })
+fn next_tab_stop(col: usize) -> usize {
+    col + 8 - col % 8
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{count, FileInfo};
-    use std::io::Cursor;
+    use super::{count, FileInfo, Scan};
+    use pretty_assertions::assert_eq;
+    use std::io::{BufReader, Cursor};
 
     #[test]
     fn test_count() {
         let text = "I don't want the world. I just want your half.\r\n";
-        let info = count(Cursor::new(text));
+        let info = count(Cursor::new(text), Scan::Text);
         assert!(info.is_ok());
         let expected = FileInfo {
             num_lines: 1,
             num_words: 10,
             num_bytes: 48,
             num_chars: 48,
+            num_invalid: 0,
+            max_line_length: 46,
         };
         assert_eq!(info.unwrap(), expected);
     }
-}
 
+    fn count_in_chunks(input: &[u8], capacity: usize) -> FileInfo {
+        count(BufReader::with_capacity(capacity, input), Scan::Text).unwrap()
+    }
+
+    #[test]
+    fn test_count_invalid_utf8() {
+        let input = b"caf\xc3\xa9 \xff\xfe bad\xe2\x82 x\n\xf0\x9f\x98";
+        let expected = FileInfo {
+            num_lines: 1,
+            num_words: 3,
+            num_bytes: input.len(),
+            num_chars: 12,
+            num_invalid: 4,
+            max_line_length: 11,
+        };
+        for capacity in 1..=input.len() {
+            assert_eq!(count_in_chunks(input, capacity), expected, "capacity {}", capacity);
+        }
+    }
+
+    #[test]
+    fn test_count_split_sequences() {
+        let input = "żółw\u{3000}日本語 \u{1f600}\u{a0}x\u{1}y\n".as_bytes();
+        let expected = FileInfo {
+            num_lines: 1,
+            num_words: 4,
+            num_bytes: input.len(),
+            num_chars: 15,
+            num_invalid: 0,
+            max_line_length: 18,
+        };
+        for capacity in 1..=input.len() {
+            assert_eq!(count_in_chunks(input, capacity), expected, "capacity {}", capacity);
+        }
+    }
+
+    #[test]
+    fn test_max_line_length() {
+        let width = |text: &str| count(text.as_bytes(), Scan::Text).unwrap().max_line_length;
+        assert_eq!(width(""), 0);
+        assert_eq!(width("abc\nab\n"), 3);
+        assert_eq!(width("no newline at end"), 17);
+        assert_eq!(width("a\tb"), 9);
+        assert_eq!(width("\t\t"), 16);
+        assert_eq!(width("1234567\tx"), 9);
+        assert_eq!(width("12345678\tx"), 17);
+        assert_eq!(width("日本語"), 6);
+        assert_eq!(width("e\u{301}e\u{301}"), 2);
+        assert_eq!(width("long line\rab"), 9);
+        assert_eq!(width("ab\u{c}abc"), 3);
+        assert_eq!(width("a\u{1}\u{7f}b"), 2);
+    }
+
+    #[test]
+    fn test_count_lines_only() {
+        let input = b"caf\xc3\xa9 \xff\tword\r\nlast line";
+        let expected = FileInfo {
+            num_lines: 1,
+            num_bytes: input.len(),
+            ..FileInfo::default()
+        };
+        assert_eq!(count(&input[..], Scan::Lines).unwrap(), expected);
+        let full = count(&input[..], Scan::Text).unwrap();
+        assert_eq!((full.num_lines, full.num_bytes), (1, input.len()));
+    }
+}
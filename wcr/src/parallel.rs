@@ -0,0 +1,160 @@
+use crate::{Counter, FileInfo, MyResult, Scan};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::thread;
+
+// Smaller parts are not worth a thread of their own.
+const MIN_PART_LEN: u64 = 1 << 20;
+
+/// Counts a regular file in up to `threads` parts at once. Returns `None`
+/// for standard input, anything that is not a regular file and files too
+/// small to split, all of which are better counted as a stream.
+pub fn count_file(
+    filename: impl AsRef<Path>,
+    threads: usize,
+    scan: Scan,
+) -> MyResult<Option<FileInfo>> {
+    let filename = filename.as_ref();
+    if filename == Path::new("-") || threads < 2 {
+        return Ok(None);
+    }
+    let metadata = fs::metadata(filename)?;
+    let parts = (metadata.len() / MIN_PART_LEN).min(threads as u64) as usize;
+    if !metadata.is_file() || parts < 2 {
+        return Ok(None);
+    }
+    let info = count_parts(|| File::open(filename), metadata.len(), parts, scan)?;
+    Ok(Some(info))
+}
+
+// Each part is read by its own thread through its own handle from `open`.
+fn count_parts<R, F>(open: F, len: u64, parts: usize, scan: Scan) -> io::Result<FileInfo>
+where
+    R: Read + Seek,
+    F: Fn() -> io::Result<R> + Sync,
+{
+    let bounds = boundaries(open()?, len, parts)?;
+    let counters = thread::scope(|scope| {
+        let handles: Vec<_> = bounds
+            .windows(2)
+            .map(|range| {
+                let (open, start, end) = (&open, range[0], range[1]);
+                scope.spawn(move || count_range(open()?, start, end, scan))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("counting thread panicked"))
+            .collect::<io::Result<Vec<_>>>()
+    })?;
+    let counter = counters.into_iter().reduce(Counter::merge).unwrap_or_default();
+    Ok(counter.finish())
+}
+
+fn count_range(
+    mut read: impl Read + Seek,
+    start: u64,
+    end: u64,
+    scan: Scan,
+) -> io::Result<Counter> {
+    read.seek(SeekFrom::Start(start))?;
+    let mut counter = Counter::new(scan);
+    counter.read_all(BufReader::with_capacity(1 << 16, read.take(end - start)))?;
+    // The next part starts with a new character, so nothing can finish
+    // a sequence left open here.
+    counter.flush_partial();
+    Ok(counter)
+}
+
+// Offsets splitting `len` bytes into evenly sized parts, each moved past
+// any UTF-8 continuation bytes so that no character is split. Words and
+// lines may still straddle parts; `Counter::merge` joins them.
+fn boundaries(mut read: impl Read + Seek, len: u64, parts: usize) -> io::Result<Vec<u64>> {
+    let mut bounds = vec![0];
+    for i in 1..parts as u64 {
+        let mut pos = (len / parts as u64 * i).max(bounds[bounds.len() - 1]);
+        read.seek(SeekFrom::Start(pos))?;
+        for byte in BufReader::new(&mut read).bytes() {
+            if byte? & 0xc0 != 0x80 {
+                break;
+            }
+            pos += 1;
+        }
+        bounds.push(pos);
+    }
+    bounds.push(len);
+    bounds.dedup();
+    Ok(bounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{boundaries, count_parts};
+    use crate::{count, Scan};
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::io::{self, Cursor};
+
+    fn count_in_parts(input: &[u8], parts: usize, scan: Scan) -> io::Result<crate::FileInfo> {
+        count_parts(|| Ok(Cursor::new(input)), input.len() as u64, parts, scan)
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let input = "aé€😀b".as_bytes();
+        let bounds = boundaries(Cursor::new(input), input.len() as u64, input.len()).unwrap();
+        assert_eq!(bounds, [0, 1, 3, 6, 10, 11]);
+        let bounds = boundaries(Cursor::new(b"\x80\x80\x80"), 3, 3).unwrap();
+        assert_eq!(bounds, [0, 3]);
+    }
+
+    #[test]
+    fn test_count_parts() {
+        let inputs = [
+            "tests/inputs/atlamal.txt",
+            "tests/inputs/binary.bin",
+            "tests/inputs/wide.txt",
+        ];
+        for filename in inputs {
+            let input = fs::read(filename).unwrap();
+            for scan in [Scan::Text, Scan::Lines] {
+                let expected = count(&input[..], scan).unwrap();
+                for parts in 1..=input.len().min(64) {
+                    assert_eq!(
+                        count_in_parts(&input, parts, scan).unwrap(),
+                        expected,
+                        "{} in {} parts, {:?}",
+                        filename,
+                        parts,
+                        scan
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_parts_straddling() {
+        let inputs: [&[u8]; 6] = [
+            b"one two\tthree\n  four",
+            b"\t\tx\ty\n12345\t\t\tz",
+            "日本\t語 \u{1}word\u{a0}x\r\nabc\tdef".as_bytes(),
+            b"wo\x01rd \xff\xfeword\xe2\x82 end",
+            b"                                                 \tx",
+            b"",
+        ];
+        for input in inputs {
+            let expected = count(input, Scan::Text).unwrap();
+            for parts in 1..=input.len() {
+                assert_eq!(
+                    count_in_parts(input, parts, Scan::Text).unwrap(),
+                    expected,
+                    "{:?} in {} parts",
+                    String::from_utf8_lossy(input),
+                    parts
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{Config, FileInfo, Format, MyResult, Total};
+use serde::Serialize;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Write};
+use std::os::fd::AsFd;
+use std::path::{Path, PathBuf};
+
+/// The counts asked for on the command line, in the order they are shown.
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    words: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chars: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_line_length: Option<usize>,
+}
+
+impl<'a> Record<'a> {
+    fn new(file: Option<&'a str>, info: &FileInfo, config: &Config) -> Record<'a> {
+        Record {
+            file,
+            lines: config.lines.then_some(info.num_lines),
+            words: config.words.then_some(info.num_words),
+            bytes: config.bytes.then_some(info.num_bytes),
+            chars: config.chars.then_some(info.num_chars),
+            max_line_length: config.max_line_length.then_some(info.max_line_length),
+        }
+    }
+
+    fn counts(&self) -> impl Iterator<Item = usize> {
+        [
+            self.lines,
+            self.words,
+            self.bytes,
+            self.chars,
+            self.max_line_length,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Prints a row of counts as soon as each file has been counted, and the
+/// total at the end.
+pub(crate) struct Report<'a, W: Write> {
+    config: &'a Config,
+    out: Out<W>,
+    rows: usize,
+}
+
+enum Out<W: Write> {
+    Table { out: W, width: usize },
+    Json(W),
+    Csv(Box<csv::Writer<W>>),
+}
+
+impl<'a, W: Write> Report<'a, W> {
+    /// `width` is that of the table columns; see `table_width`.
+    pub(crate) fn new(out: W, config: &'a Config, width: usize) -> Report<'a, W> {
+        let out = match config.format {
+            Format::Table => Out::Table { out, width },
+            Format::Json => Out::Json(out),
+            Format::Csv | Format::Tsv => {
+                let delimiter = if config.format == Format::Tsv { b'\t' } else { b',' };
+                Out::Csv(Box::new(
+                    csv::WriterBuilder::new().delimiter(delimiter).from_writer(out),
+                ))
+            }
+        };
+        Report { config, out, rows: 0 }
+    }
+
+    pub(crate) fn file(&mut self, filename: &str, info: &FileInfo) -> MyResult<()> {
+        let record = Record::new(Some(filename), info, self.config);
+        match &mut self.out {
+            Out::Table { out, width } => write_row(out, *width, &record)?,
+            // The same layout as serde_json's pretty printer would give the
+            // whole report, but without waiting for the last file.
+            Out::Json(out) => {
+                let sep = if self.rows == 0 { "{\n  \"files\": [" } else { "," };
+                write!(out, "{}\n    ", sep)?;
+                write_indented(out, &record, "    ")?;
+            }
+            Out::Csv(writer) => {
+                writer.serialize(record)?;
+                writer.flush()?;
+            }
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    pub(crate) fn finish(self, total: Option<&FileInfo>) -> MyResult<()> {
+        let config = self.config;
+        match self.out {
+            Out::Table { mut out, width } => {
+                // Standard input has no name, and neither does a total on its own.
+                let name = (config.total != Total::Only).then_some("total");
+                if let Some(info) = total {
+                    write_row(&mut out, width, &Record::new(name, info, config))?;
+                }
+            }
+            Out::Json(mut out) => {
+                match self.rows {
+                    0 => write!(out, "{{\n  \"files\": []")?,
+                    _ => write!(out, "\n  ]")?,
+                }
+                if let Some(info) = total {
+                    write!(out, ",\n  \"total\": ")?;
+                    write_indented(&mut out, &Record::new(None, info, config), "  ")?;
+                }
+                writeln!(out, "\n}}")?;
+            }
+            Out::Csv(mut writer) => {
+                if let Some(info) = total {
+                    writer.serialize(Record::new(Some("total"), info, config))?;
+                }
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Pretty prints `value` as if it were nested `indent` deep.
+fn write_indented(out: &mut impl Write, value: &impl Serialize, indent: &str) -> MyResult<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    write!(out, "{}", json.replace('\n', &format!("\n{}", indent)))?;
+    Ok(())
+}
+
+/// Chooses the table column width before anything is counted, as GNU wc
+/// does: wide enough for the total size of the regular files, and at least
+/// 7 if some input has no size known in advance. A single count of a
+/// single file is not padded at all, and neither are files named by a
+/// --files0-from stream, which GNU wc counts as the names come in.
+pub(crate) fn table_width(files: &[PathBuf], config: &Config) -> usize {
+    let streamed = config
+        .files0_from
+        .as_ref()
+        .is_some_and(|source| !metadata(Path::new(source)).is_ok_and(|m| m.is_file()));
+    if streamed || (files.len() == 1 && config.counts() == 1) {
+        return 1;
+    }
+    let mut minimum = 1;
+    let mut regular_total: u64 = 0;
+    // Files that cannot be looked at will fail to open too.
+    for metadata in files.iter().filter_map(|file| metadata(file).ok()) {
+        if metadata.is_file() {
+            regular_total = regular_total.saturating_add(metadata.len());
+        } else {
+            minimum = 7;
+        }
+    }
+    regular_total.to_string().len().max(minimum)
+}
+
+fn metadata(filename: &Path) -> io::Result<Metadata> {
+    match filename.to_str() {
+        Some("-") => File::from(io::stdin().as_fd().try_clone_to_owned()?).metadata(),
+        _ => fs::metadata(filename),
+    }
+}
+
+fn write_row(out: &mut impl Write, width: usize, row: &Record) -> io::Result<()> {
+    let counts: Vec<_> = row.counts().map(|n| format!("{:>width$}", n)).collect();
+    write!(out, "{}", counts.join(" "))?;
+    match row.file {
+        Some(file) if file != "-" => writeln!(out, " {}", file),
+        _ => writeln!(out),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{table_width, Report};
+    use crate::{Config, FileInfo};
+    use clap::Parser;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn config(args: &[&str]) -> Config {
+        let mut config = Config::parse_from([&["wcr"], args].concat());
+        config.finalize();
+        config
+    }
+
+    fn report(args: &[&str], files: &[(&str, FileInfo)], total: Option<&FileInfo>) -> String {
+        let config = config(args);
+        let mut out = Vec::new();
+        let mut report = Report::new(&mut out, &config, 3);
+        for (filename, info) in files {
+            report.file(filename, info).unwrap();
+        }
+        report.finish(total).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn info(lines: usize, words: usize, bytes: usize) -> FileInfo {
+        FileInfo {
+            num_lines: lines,
+            num_words: words,
+            num_bytes: bytes,
+            ..FileInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_table_rows() {
+        let files = [("a.txt", info(1, 9, 48)), ("-", info(0, 0, 0))];
+        assert_eq!(report(&[], &files, None), "  1   9  48 a.txt\n  0   0   0\n");
+        let total = info(1_000_000_000, 9, 48);
+        assert_eq!(
+            report(&["-l"], &files[..1], Some(&total)),
+            "  1 a.txt\n1000000000 total\n"
+        );
+        assert_eq!(report(&["--total=only"], &[], Some(&total)), "1000000000   9  48\n");
+        assert_eq!(report(&[], &[], None), "");
+    }
+
+    #[test]
+    fn test_table_width() {
+        let width = |args: &[&str], files: &[&str]| {
+            let files: Vec<_> = files.iter().map(PathBuf::from).collect();
+            table_width(&files, &config(args))
+        };
+        let (fox, atlamal) = ("tests/inputs/fox.txt", "tests/inputs/atlamal.txt");
+        assert_eq!(width(&[], &[fox]), 2);
+        assert_eq!(width(&["-l"], &[fox]), 1);
+        assert_eq!(width(&["-l"], &[fox, atlamal]), 3);
+        assert_eq!(width(&[], &[fox, "tests/inputs"]), 7);
+        assert_eq!(width(&[], &[fox, "no/such/file"]), 2);
+        assert_eq!(width(&[], &[]), 1);
+    }
+
+    #[test]
+    fn test_machine_formats() {
+        let files = [("a,b.txt", info(1, 9, 48))];
+        let total = info(1, 9, 48);
+        assert_eq!(
+            report(&["--format=csv"], &files, Some(&total)),
+            "file,lines,words,bytes\n\"a,b.txt\",1,9,48\ntotal,1,9,48\n"
+        );
+        assert_eq!(
+            report(&["--format=tsv", "-L"], &files, None),
+            "file\tmax_line_length\na,b.txt\t0\n"
+        );
+        assert_eq!(
+            report(&["--format=json", "-wm"], &files, Some(&total)),
+            r#"{
+  "files": [
+    {
+      "file": "a,b.txt",
+      "words": 9,
+      "chars": 0
+    }
+  ],
+  "total": {
+    "words": 9,
+    "chars": 0
+  }
+}
+"#
+        );
+        let twice = [("a,b.txt", info(1, 9, 48)), ("a,b.txt", info(1, 9, 48))];
+        assert_eq!(
+            report(&["--format=json", "-l"], &twice, None),
+            "{\n  \"files\": [\n    {\n      \"file\": \"a,b.txt\",\n      \"lines\": 1\n    },\n    \
+            {\n      \"file\": \"a,b.txt\",\n      \"lines\": 1\n    }\n  ]\n}\n"
+        );
+        assert_eq!(report(&["--format=json"], &[], None), "{\n  \"files\": []\n}\n");
+    }
+}
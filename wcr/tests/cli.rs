@@ -3,12 +3,18 @@ use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
 use rand::{distributions::Alphanumeric, Rng};
+use std::ffi::OsStr;
 use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use tempfile::NamedTempFile;
 
 const PRG: &str = "wcr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+const BINARY: &str = "tests/inputs/binary.bin";
+const WIDE: &str = "tests/inputs/wide.txt";
+const FILES0: &str = "tests/inputs/files0.txt";
 
 // --------------------------------------------------
 fn gen_bad_file() -> String {
@@ -63,6 +69,31 @@ fn skips_bad_file() -> Result<()> {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn skips_directory_and_continues() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([FOX, "tests/inputs", FOX])
+        .assert()
+        .success()
+        .stdout(format!(
+            "      1       9      48 {FOX}\n      1       9      48 {FOX}\n      2      18      96 total\n"
+        ))
+        .stderr("tests/inputs: Is a directory (os error 21)\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_pipe_width() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .write_stdin("abc\n")
+        .assert()
+        .success()
+        .stdout("      1       1       4\n");
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn empty() -> Result<()> {
@@ -218,3 +249,298 @@ fn test_all_words_lines() -> Result<()> {
 fn test_all_bytes_lines() -> Result<()> {
     run(&["-cl", EMPTY, FOX, ATLAMAL], "tests/expected/all.cl.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn binary() -> Result<()> {
+    run(&[BINARY], "tests/expected/binary.bin.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_lines_words_chars() -> Result<()> {
+    run(&["-lwm", BINARY], "tests/expected/binary.bin.lwm.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_report_invalid() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--chars", "--report-invalid", BINARY])
+        .assert()
+        .success()
+        .stdout(format!("150 {BINARY}\n"))
+        .stderr(format!("{BINARY}: 132 invalid UTF-8 sequences\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_stdin() -> Result<()> {
+    let input = fs::read(BINARY)?;
+    Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("      3       5     291\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn wide_max_line_length() -> Result<()> {
+    run(&["-L", WIDE], "tests/expected/wide.txt.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn wide_all_counts() -> Result<()> {
+    run(&["-lwmL", WIDE], "tests/expected/wide.txt.lwmL.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_max_line_length() -> Result<()> {
+    run(
+        &["--max-line-length", EMPTY, FOX, ATLAMAL, WIDE],
+        "tests/expected/all.L.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_max_line_length() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-L", BINARY])
+        .assert()
+        .success()
+        .stdout(format!("103 {BINARY}\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from() -> Result<()> {
+    run(&["--files0-from", FILES0], "tests/expected/all.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_stdin() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--files0-from=-"])
+        .write_stdin(format!("{FOX}\0\0-\0{EMPTY}\0"))
+        .assert()
+        .success()
+        .stdout(format!("1 {FOX}\n0 {EMPTY}\n1 total\n"))
+        .stderr(
+            "-:2: invalid zero-length file name\n\
+             when reading file names from standard input, no file name of '-' allowed\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_non_utf8_name() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join(OsStr::from_bytes(b"caf\xe9"));
+    fs::copy(FOX, &path)?;
+    let mut input = path.as_os_str().as_bytes().to_vec();
+    input.push(b'\0');
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from=-"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(format!("1 9 48 {}\n", path.display()));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files0_from_and_files() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", FILES0, FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the argument '--files0-from <F>' cannot be used with '[FILE]...'",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files0_from_bad_file() -> Result<()> {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", &bad])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "cannot open '{bad}' for reading"
+        )));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn total_only() -> Result<()> {
+    run(
+        &["--total=only", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total-only.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn total_always() -> Result<()> {
+    run(
+        &["--total", "always", FOX],
+        "tests/expected/fox.txt.total-always.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn total_never() -> Result<()> {
+    run(
+        &["--total=never", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total-never.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_total() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--total=sometimes", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value 'sometimes' for '--total <WHEN>'",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn threads_large_file() -> Result<()> {
+    // Big enough to be split, with words, tabs and characters straddling
+    // the parts.
+    let mut input = fs::read(ATLAMAL)?;
+    input.extend(fs::read(BINARY)?);
+    input.extend(fs::read(WIDE)?);
+    let input = input.repeat(3 << 20 >> 9);
+    let file = NamedTempFile::new()?;
+    fs::write(file.path(), &input)?;
+    let path = file.path().to_str().unwrap();
+
+    let expected = Command::cargo_bin(PRG)?.args(["-lwmL", path]).output()?;
+    assert!(expected.status.success());
+    for threads in ["2", "3", "8"] {
+        Command::cargo_bin(PRG)?
+            .args(["-lwmL", "--threads", threads, path])
+            .assert()
+            .success()
+            .stdout(expected.stdout.clone());
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn threads_small_file() -> Result<()> {
+    run(&["--threads", "4", FOX], "tests/expected/fox.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_zero_threads() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--threads", "0", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value '0' for '--threads <N>': must be at least 1",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn format_json() -> Result<()> {
+    run(
+        &["--format", "json", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn format_json_total_only() -> Result<()> {
+    run(
+        &["--format=json", "--total=only", "-c", FOX, ATLAMAL],
+        "tests/expected/all.c.total-only.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn format_csv() -> Result<()> {
+    run(
+        &["--format=csv", "-lwmL", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.lwmL.csv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn format_tsv() -> Result<()> {
+    run(
+        &["--format=tsv", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.tsv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn format_csv_stdin() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--format=csv"])
+        .write_stdin(fs::read(FOX)?)
+        .assert()
+        .success()
+        .stdout("file,lines,words,bytes\n-,1,9,48\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn table_widens_for_large_counts() -> Result<()> {
+    let file = NamedTempFile::new()?;
+    fs::write(file.path(), "\n".repeat(1_234_567))?;
+    let path = file.path().to_str().unwrap();
+    Command::cargo_bin(PRG)?
+        .args(["-lc", path, FOX])
+        .assert()
+        .success()
+        .stdout(format!(
+            "1234567 1234567 {path}\n      1      48 {FOX}\n1234568 1234615 total\n"
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_format() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--format=xml", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value 'xml' for '--format <FORMAT>'",
+        ));
+    Ok(())
+}